@@ -0,0 +1,70 @@
+use std::{cell::RefCell, rc::Rc};
+use crate::LoxError;
+use crate::interpreter::{environment::Environment, gc::Heap};
+use crate::interpreter::interpret::Interpreter;
+use crate::types::values::{Value, class::LoxClass, generator::LoxGenerator};
+use crate::vm::machine::Vm;
+
+/// The interpreter state given to native functions when they are called.
+///
+/// Through the context, a native function can call back into Lox, e.g. to apply a closure it was given as an argument.
+pub struct NativeContext<'a> {
+    caller: Caller<'a>
+}
+
+/// The backend running the Lox code that called the native function.
+enum Caller<'a> {
+    TreeWalker(&'a mut Interpreter, &'a Environment),
+    Vm(&'a mut Vm)
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(interpreter: &'a mut Interpreter, env: &'a Environment) -> Self {
+        Self { caller: Caller::TreeWalker(interpreter, env) }
+    }
+
+    pub(crate) fn from_vm(vm: &'a mut Vm) -> Self {
+        Self { caller: Caller::Vm(vm) }
+    }
+
+    /// Calls a callable or constructs a class, in the same way as a call expression would.
+    pub fn call(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, LoxError> {
+        match &mut self.caller {
+            Caller::TreeWalker(interpreter, env) => interpreter.call_value(callee, args, env),
+            Caller::Vm(vm) => vm.call_value(callee, args)
+        }
+    }
+
+    /// Constructs a new instance of a class.
+    pub fn instantiate(&mut self, class: Rc<LoxClass>, args: Vec<Value>) -> Result<Value, LoxError> {
+        match &mut self.caller {
+            Caller::TreeWalker(interpreter, env) => interpreter.instantiate(class, args, env),
+            Caller::Vm(vm) => vm.call_value(Value::Class(class), args)
+        }
+    }
+
+    /// Resumes a generator, returning the value it yields or returns. The yield it stopped at evaluates to the value
+    /// it is resumed with.
+    pub fn resume(&mut self, generator: &Rc<RefCell<LoxGenerator>>, sent: Value) -> Result<Value, LoxError> {
+        match &mut self.caller {
+            Caller::TreeWalker(interpreter, _) => interpreter.resume(generator, sent),
+            Caller::Vm(vm) => vm.resume(generator, sent)
+        }
+    }
+
+    /// Retrieves a global binding.
+    pub fn get_global(&self, name: &str) -> Result<Value, LoxError> {
+        match &self.caller {
+            Caller::TreeWalker(_, env) => env.get_global(name),
+            Caller::Vm(vm) => vm.get_global(name)
+        }
+    }
+
+    /// Returns the heap which tracks the objects of the running program.
+    pub fn heap(&self) -> &Heap {
+        match &self.caller {
+            Caller::TreeWalker(_, env) => env.heap(),
+            Caller::Vm(vm) => vm.heap()
+        }
+    }
+}
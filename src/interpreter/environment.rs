@@ -1,158 +1,204 @@
-use std::{collections::{HashMap, HashSet}, ops};
-use crate::{LoxError, interpreter::stdlib, types::{token::Token, values::Value}};
-use crate::types::expr::Expr;
-use crate::types::statement::Statement;
+use std::{cell::RefCell, collections::HashMap, fmt, path::{Path, PathBuf}, rc::Rc};
+use crate::{LoxError, interpreter::{context::NativeContext, gc::{Heap, Trace, address}, modules::Modules, stdlib}, types::{expr::Depth, token::Token, values::{Value, callable::{LoxCallable, NativeFn}}}};
 
-#[derive(Clone, Debug, PartialEq)]
+/// The bindings visible from a point in a Lox program.
+///
+/// Local bindings are kept in a chain of shared scopes, starting from the innermost scope. Closures keep a reference
+/// to the scope they were declared in, so they share its variables with the code around them. The resolver gives every
+/// local variable a depth, which is the number of scopes between its use and its declaration. Globals are shared by
+/// every environment created from the same root environment, as is the heap which tracks their objects. Imported
+/// modules get their own globals, which start out with the standard library and the natives registered by the host, and
+/// share the heap and the loaded modules with the program importing them.
+#[derive(Clone)]
 pub struct Environment {
-    locals: HashMap<String, Value>,
-    globals: HashMap<String, Value>,
-    assignments: HashMap<String, Value>,
-    declared_here_in_block: HashSet<String>,
-    is_block_env: bool
+    scope: Option<Rc<RefCell<Scope>>>,
+    globals: Rc<RefCell<HashMap<String, Value>>>,
+    heap: Heap,
+    modules: Rc<RefCell<Modules>>,
+    // Native functions registered by the host, which every module can call
+    natives: Rc<RefCell<HashMap<String, Value>>>,
+    // The file the code run in this environment comes from, which imports are relative to
+    path: Option<Rc<PathBuf>>
+}
+
+/// A single scope of local bindings.
+#[derive(Default)]
+pub(crate) struct Scope {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Scope>>>
+}
+
+// Environments are compared by identity, as they can contain closures that refer back to them.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        let same_scope: bool = match (&self.scope, &other.scope) {
+            (Some(scope), Some(other_scope)) => Rc::ptr_eq(scope, other_scope),
+            (None, None) => true,
+            _ => false
+        };
+
+        same_scope && Rc::ptr_eq(&self.globals, &other.globals)
+    }
+}
+
+// Bindings are left out for the same reason.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Environment")
+         .field("depth", &self.depth())
+         .finish_non_exhaustive()
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
     pub fn new() -> Self {
-        Self { 
-            locals: HashMap::new(),
-            globals: stdlib::get_stdlib(),
-            assignments: HashMap::new(),
-            declared_here_in_block: HashSet::new(),
-            is_block_env: false
+        Self {
+            scope: None,
+            globals: Rc::new(RefCell::new(stdlib::get_stdlib())),
+            heap: Heap::new(),
+            modules: Rc::new(RefCell::new(Modules::default())),
+            natives: Rc::new(RefCell::new(HashMap::new())),
+            path: None
         }
     }
 
-    pub fn from(env: Environment) -> Self {
-        Self { 
-            locals: env.locals,
-            globals: env.globals,
-            assignments: env.assignments,
-            declared_here_in_block: env.declared_here_in_block,
-            is_block_env: env.is_block_env
+    /// Creates the environment of a module loaded from a file. It has its own globals, and shares everything else.
+    pub(crate) fn module(&self, path: PathBuf) -> Self {
+        let mut globals: HashMap<String, Value> = stdlib::get_stdlib();
+        globals.extend(self.natives.borrow().iter().map(|(name, native)| (name.clone(), native.clone())));
+
+        Self {
+            scope: None,
+            globals: Rc::new(RefCell::new(globals)),
+            heap: self.heap.clone(),
+            modules: self.modules.clone(),
+            natives: self.natives.clone(),
+            path: Some(Rc::new(path))
         }
     }
 
-    /// Constructs a new environment from a vector of tokens and values. This does not perform arity checks: it simply returns when a vector is drained.
-    pub fn build(names: &Vec<Token>, values: &Vec<Value>) -> Self {
-        let mut build_values: HashMap<String, Value> = HashMap::new();
-        let mut names = names.iter();
-        let mut values = values.iter();
+    /// Sets the file the program run in this environment comes from. Imports are looked up relative to it, and the
+    /// file can't be imported by the modules it imports.
+    pub fn set_path(&mut self, path: PathBuf) {
+        let path: PathBuf = path.canonicalize().unwrap_or(path);
+        self.modules.borrow_mut().set_root(path.clone());
+        self.path = Some(Rc::new(path));
+    }
 
-        loop {
-            let next_name: Option<&Token> = names.next();
-            let next_value: Option<&Value> = values.next();
+    /// Returns the file the program run in this environment comes from, if it came from a file.
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_deref().map(PathBuf::as_path)
+    }
 
-            if next_name.is_none() || next_value.is_none() {
-                break;
-            }
+    /// Returns the modules loaded by the program.
+    pub(crate) fn modules(&self) -> &Rc<RefCell<Modules>> {
+        &self.modules
+    }
 
-            let next_name: Token = next_name.unwrap().clone();
-            let next_value: Value = next_value.unwrap().clone();
-            build_values.insert(next_name.lexeme, next_value);
-        }
+    /// Returns the heap which tracks the objects created in this environment.
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
 
-        Self {
-            locals: build_values,
-            globals: HashMap::new(),
-            assignments: HashMap::new(),
-            declared_here_in_block: HashSet::new(),
-            is_block_env: false
-        }
+    /// Starts a new innermost scope.
+    pub fn push_scope(&mut self) {
+        self.push_scope_with(&[], Vec::new());
     }
 
-    /// Creates an environment from an existing environment, with assignments wiped.
-    pub fn get_block_env(env: &Environment) -> Self {
-        Self {
-            locals: env.locals.clone(),
-            globals: env.globals.clone(),
-            assignments: HashMap::new(),
-            declared_here_in_block: HashSet::new(),
-            is_block_env: true
+    /// Discards the innermost scope. Closures declared in the scope keep it alive.
+    pub fn pop_scope(&mut self) {
+        if let Some(scope) = self.scope.take() {
+            self.scope = scope.borrow().enclosing.clone();
         }
     }
 
-    /// Adds the globals from another environment.
-    pub fn add_globals(&mut self, other: &Environment) {
-        self.globals.extend(other.globals.clone());
+    /// Starts a new innermost scope from a vector of tokens and values. This does not perform arity checks: it simply
+    /// stops when a vector is drained.
+    pub fn push_scope_with(&mut self, names: &[Token], values: Vec<Value>) {
+        let values: HashMap<String, Value> = names.iter()
+                                                  .map(|name| name.lexeme.clone())
+                                                  .zip(values)
+                                                  .collect();
+        let scope = Scope { values, enclosing: self.scope.take() };
+        self.scope = Some(self.heap.scope(scope));
     }
 
-    /// Defines a local environment binding
-    pub fn define_local(&mut self, name: String, value: Value) {
-        if self.is_block_env {
-            self.declared_here_in_block.insert(name.clone());
+    /// Defines a binding in the innermost scope, or a global if there are no local scopes.
+    pub fn define(&mut self, name: String, value: Value) {
+        match &self.scope {
+            Some(scope) => { scope.borrow_mut().values.insert(name, value); },
+            None => self.define_global(name, value)
         }
-        
-        self.locals.insert(name, value);
     }
 
     /// Defines a global (exists in all function calls) environment binding
-    pub fn define_global(&mut self, name: String, value: Value) -> Result<(), LoxError> {
-        if self.is_block_env {
-            Err(LoxError::CompilerBug(Statement::Expression(Expr::Literal(Value::Str(name))),
-                String::from("Globals cannot be defined in local scope.")))
-        }
-        
-        else {
-            self.globals.insert(name.clone(), value);
-            Ok(())
-        }
+    pub fn define_global(&mut self, name: String, value: Value) {
+        self.globals.borrow_mut().insert(name, value);
     }
-    
-    /// Retrieves an envrionment binding
-    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
-        if self.locals.contains_key(&name.lexeme) {
-            Ok(self.locals[&name.lexeme].clone())
-        }
-        
-        else if self.globals.contains_key(&name.lexeme) {
-            Ok(self.globals[&name.lexeme].clone())
-        }
 
-        else {
-            Err(LoxError::NameError(name.lexeme.clone(), String::from("Undefined variable.")))
-        }
+    /// Defines a global native function, which can be called from any Lox code run in this environment, and from the
+    /// modules it imports afterwards.
+    pub fn define_native(&mut self, name: &str, arity: usize, func: impl Fn(&mut NativeContext, Vec<Value>) -> Result<Value, LoxError> + 'static) {
+        let native: Value = Value::Callable(LoxCallable::Native(String::from(name), NativeFn::new(func), arity));
+        self.natives.borrow_mut().insert(String::from(name), native.clone());
+        self.define_global(String::from(name), native)
     }
 
-    pub fn get_by_str(&self, name: &str) -> Result<Value, LoxError> {
-        if self.locals.contains_key(name) {
-            Ok(self.locals[name].clone())
-        }
-        
-        else if self.globals.contains_key(name) {
-            Ok(self.globals[name].clone())
-        }
+    /// Retrieves a binding resolved to a depth, or a global binding if it has no depth.
+    pub fn get_at(&self, depth: Depth, name: &Token) -> Result<Value, LoxError> {
+        let value: Option<Value> = match depth {
+            Some(depth) => self.scope_at(depth).and_then(|scope| scope.borrow().values.get(&name.lexeme).cloned()),
+            None => self.globals.borrow().get(&name.lexeme).cloned()
+        };
 
-        else {
-            Err(LoxError::NameError(String::from(name), String::from("Undefined variable.")))
-        }
+        value.ok_or_else(|| LoxError::NameError(name.lexeme.clone(), String::from("Undefined variable.")))
+    }
+
+    /// Retrieves an envrionment binding, searching from the innermost scope outwards
+    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
+        self.get_by_str(&name.lexeme)
     }
 
-    /// Reassigns an existing environment binding.
-    pub fn assign(&mut self, name: Token, value: Value) -> Result<(), LoxError> {
-        // Locals case
-        if self.locals.contains_key(&name.lexeme) {
-            if self.is_block_env && !self.declared_here_in_block.contains(&name.lexeme) {
-                self.assignments.insert(name.lexeme.clone(), value.clone());
+    pub fn get_by_str(&self, name: &str) -> Result<Value, LoxError> {
+        let mut scope: Option<Rc<RefCell<Scope>>> = self.scope.clone();
+
+        while let Some(current) = scope {
+            if let Some(value) = current.borrow().values.get(name) {
+                return Ok(value.clone());
             }
 
-            self.locals.insert(name.lexeme, value);
+            scope = current.borrow().enclosing.clone();
+        }
 
-            Ok(())
+        self.get_global(name)
+    }
+
+    /// Retrieves a global binding.
+    pub fn get_global(&self, name: &str) -> Result<Value, LoxError> {
+        match self.globals.borrow().get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(LoxError::NameError(String::from(name), String::from("Undefined variable.")))
         }
+    }
 
-        // Globals case
-        else if self.globals.contains_key(&name.lexeme) {
-            // Shadow the value in locals if declared in block
-            if self.is_block_env {
-                self.locals.insert(name.lexeme, value);
-                Ok(())
-            }
+    /// Reassigns an existing binding resolved to a depth, or a global binding if it has no depth.
+    pub fn assign_at(&mut self, depth: Depth, name: Token, value: Value) -> Result<(), LoxError> {
+        let assigned: bool = match depth {
+            Some(depth) => match self.scope_at(depth) {
+                Some(scope) => assign_in(&mut scope.borrow_mut().values, &name, value),
+                None => false
+            },
+            None => assign_in(&mut self.globals.borrow_mut(), &name, value)
+        };
 
-            else {
-                self.globals.insert(name.lexeme, value);
-                Ok(())
-            }
+        if assigned {
+            Ok(())
         }
 
         else {
@@ -161,30 +207,58 @@ impl Environment {
         }
     }
 
-    /// Adds assignments from block environments to current environment
-    pub fn add_assignments(&mut self, block_env: &mut Environment) {
-        for (name, value) in block_env.assignments.drain() {
-            // Propagate values forward if this is a block environment
-            if self.is_block_env && !self.declared_here_in_block.contains(&name) {
-                self.assignments.insert(name.clone(), value.clone());
-            }
+    /// Returns the scope a number of scopes out from the innermost scope.
+    fn scope_at(&self, depth: usize) -> Option<Rc<RefCell<Scope>>> {
+        let mut scope: Rc<RefCell<Scope>> = self.scope.clone()?;
+
+        for _ in 0..depth {
+            let enclosing: Rc<RefCell<Scope>> = scope.borrow().enclosing.clone()?;
+            scope = enclosing;
+        }
+
+        Some(scope)
+    }
+
+    /// Returns the number of local scopes in the environment.
+    fn depth(&self) -> usize {
+        let mut depth: usize = 0;
+        let mut scope: Option<Rc<RefCell<Scope>>> = self.scope.clone();
 
-            self.locals.insert(name, value);
+        while let Some(current) = scope {
+            depth += 1;
+            scope = current.borrow().enclosing.clone();
         }
+
+        depth
     }
+}
 
-    /// Returns whether an environment is defined in global or local scope
-    pub fn is_global(&self) -> bool {
-        !self.is_block_env
+// Globals are not traced, as they are always reachable.
+impl Trace for Environment {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        if let Some(scope) = &self.scope {
+            edges.push(address(scope));
+        }
     }
 }
 
-impl ops::Add<Environment> for Environment {
-    type Output = Environment;
+impl Trace for Scope {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        self.values.values().for_each(|value| value.trace(edges));
+
+        if let Some(enclosing) = &self.enclosing {
+            edges.push(address(enclosing));
+        }
+    }
+}
 
-    fn add(mut self, _rhs: Environment) -> Environment {
-        self.locals.extend(_rhs.locals);
-        self.globals.extend(_rhs.globals);
-        self
+/// Reassigns an existing binding in a map of bindings, returning whether the binding existed.
+fn assign_in(values: &mut HashMap<String, Value>, name: &Token, value: Value) -> bool {
+    match values.get_mut(&name.lexeme) {
+        Some(binding) => {
+            *binding = value;
+            true
+        },
+        None => false
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,404 @@
+use std::{cell::RefCell, collections::HashMap, rc::{Rc, Weak}};
+use crate::interpreter::environment::Scope;
+use crate::types::values::{Value, class::LoxClass, generator::LoxGenerator, iterator::LoxIterator, list::LoxList, map::LoxMap, object::LoxObject};
+use crate::vm::function::{Closure, Upvalue};
+
+/// The number of tracked allocations between automatic collections, while few objects are alive.
+const COLLECT_THRESHOLD: usize = 10000;
+
+/// Collects reference cycles between Lox objects.
+///
+/// Values are reference counted, so they are freed as soon as nothing refers to them, except when they refer to each
+/// other in a cycle. The heap keeps a weak reference to every object which can be part of a cycle. A collection finds
+/// the objects which are only referred to by other tracked objects, and are not reachable from anything else, and
+/// clears them so their cycles are freed. Collections run automatically as objects are allocated, or when `gc()` is
+/// called.
+#[derive(Clone)]
+pub struct Heap(Rc<RefCell<HeapState>>);
+
+struct HeapState {
+    tracked: Vec<Tracked>,
+    allocations: usize,
+    threshold: usize,
+    stats: GcStats
+}
+
+/// Statistics about the collections a heap has run.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GcStats {
+    /// The number of collections run.
+    pub collections: usize,
+    /// The number of instances freed.
+    pub instances: usize,
+    /// The number of classes freed.
+    pub classes: usize,
+    /// The number of lists freed.
+    pub lists: usize,
+    /// The number of maps freed.
+    pub maps: usize,
+    /// The number of iterators over built-in values freed.
+    pub iterators: usize,
+    /// The number of generators freed.
+    pub generators: usize,
+    /// The number of scopes of local variables freed.
+    pub scopes: usize,
+    /// The number of compiled closures freed.
+    pub closures: usize,
+    /// The number of variables captured by compiled closures freed.
+    pub upvalues: usize,
+    /// The number of objects still tracked after the last collection.
+    pub tracked: usize
+}
+
+impl GcStats {
+    /// Returns the total number of objects freed.
+    pub fn collected(&self) -> usize {
+        self.instances + self.classes + self.lists + self.maps + self.iterators + self.generators + self.scopes + self.closures + self.upvalues
+    }
+}
+
+/// Objects which refer to objects tracked by the heap.
+pub(crate) trait Trace {
+    /// Adds the address of every tracked object this object refers to directly.
+    fn trace(&self, edges: &mut Vec<usize>);
+}
+
+/// Returns the address a tracked object is identified by.
+pub(crate) fn address<T: ?Sized>(object: &Rc<T>) -> usize {
+    Rc::as_ptr(object) as *const () as usize
+}
+
+/// A weak reference to a tracked object.
+enum Tracked {
+    Instance(Weak<RefCell<LoxObject>>),
+    Class(Weak<LoxClass>),
+    List(Weak<RefCell<LoxList>>),
+    Map(Weak<RefCell<LoxMap>>),
+    Iterator(Weak<RefCell<LoxIterator>>),
+    Generator(Weak<RefCell<LoxGenerator>>),
+    Scope(Weak<RefCell<Scope>>),
+    Closure(Weak<Closure>),
+    Upvalue(Weak<RefCell<Upvalue>>)
+}
+
+/// A tracked object which is still alive during a collection.
+enum Live {
+    Instance(Rc<RefCell<LoxObject>>),
+    Class(Rc<LoxClass>),
+    List(Rc<RefCell<LoxList>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Iterator(Rc<RefCell<LoxIterator>>),
+    Generator(Rc<RefCell<LoxGenerator>>),
+    Scope(Rc<RefCell<Scope>>),
+    Closure(Rc<Closure>),
+    Upvalue(Rc<RefCell<Upvalue>>)
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        let state = HeapState {
+            tracked: Vec::new(),
+            allocations: 0,
+            threshold: COLLECT_THRESHOLD,
+            stats: GcStats::default()
+        };
+
+        Self(Rc::new(RefCell::new(state)))
+    }
+
+    pub(crate) fn instance(&self, object: LoxObject) -> Rc<RefCell<LoxObject>> {
+        let object: Rc<RefCell<LoxObject>> = Rc::new(RefCell::new(object));
+        self.track(Tracked::Instance(Rc::downgrade(&object)));
+        object
+    }
+
+    pub(crate) fn class(&self, class: LoxClass) -> Rc<LoxClass> {
+        let class: Rc<LoxClass> = Rc::new(class);
+        self.track(Tracked::Class(Rc::downgrade(&class)));
+        class
+    }
+
+    pub(crate) fn list(&self, list: LoxList) -> Rc<RefCell<LoxList>> {
+        let list: Rc<RefCell<LoxList>> = Rc::new(RefCell::new(list));
+        self.track(Tracked::List(Rc::downgrade(&list)));
+        list
+    }
+
+    pub(crate) fn map(&self, map: LoxMap) -> Rc<RefCell<LoxMap>> {
+        let map: Rc<RefCell<LoxMap>> = Rc::new(RefCell::new(map));
+        self.track(Tracked::Map(Rc::downgrade(&map)));
+        map
+    }
+
+    pub(crate) fn iterator(&self, iterator: LoxIterator) -> Rc<RefCell<LoxIterator>> {
+        let iterator: Rc<RefCell<LoxIterator>> = Rc::new(RefCell::new(iterator));
+        self.track(Tracked::Iterator(Rc::downgrade(&iterator)));
+        iterator
+    }
+
+    pub(crate) fn generator(&self, generator: LoxGenerator) -> Rc<RefCell<LoxGenerator>> {
+        let generator: Rc<RefCell<LoxGenerator>> = Rc::new(RefCell::new(generator));
+        self.track(Tracked::Generator(Rc::downgrade(&generator)));
+        generator
+    }
+
+    pub(crate) fn scope(&self, scope: Scope) -> Rc<RefCell<Scope>> {
+        let scope: Rc<RefCell<Scope>> = Rc::new(RefCell::new(scope));
+        self.track(Tracked::Scope(Rc::downgrade(&scope)));
+        scope
+    }
+
+    pub(crate) fn closure(&self, closure: Closure) -> Rc<Closure> {
+        let closure: Rc<Closure> = Rc::new(closure);
+        self.track(Tracked::Closure(Rc::downgrade(&closure)));
+        closure
+    }
+
+    pub(crate) fn upvalue(&self, upvalue: Upvalue) -> Rc<RefCell<Upvalue>> {
+        let upvalue: Rc<RefCell<Upvalue>> = Rc::new(RefCell::new(upvalue));
+        self.track(Tracked::Upvalue(Rc::downgrade(&upvalue)));
+        upvalue
+    }
+
+    /// Returns statistics about the collections run so far.
+    pub fn stats(&self) -> GcStats {
+        self.0.borrow().stats
+    }
+
+    /// Frees every cycle of objects which can no longer be reached, returning the number of objects freed.
+    pub fn collect(&self) -> usize {
+        let nodes: Vec<Live> = self.0.borrow_mut()
+                                     .tracked
+                                     .drain(..)
+                                     .filter_map(|tracked| tracked.upgrade())
+                                     .collect();
+
+        let garbage: Vec<bool> = find_garbage(&nodes);
+        let mut stats: GcStats = self.stats();
+        let mut survivors: Vec<Tracked> = Vec::new();
+
+        for (node, is_garbage) in nodes.iter().zip(&garbage) {
+            if *is_garbage {
+                node.clear(&mut stats);
+            }
+
+            else {
+                survivors.push(node.downgrade());
+            }
+        }
+
+        let collected: usize = garbage.iter().filter(|is_garbage| **is_garbage).count();
+        stats.collections += 1;
+        stats.tracked = survivors.len();
+
+        let mut state = self.0.borrow_mut();
+        state.threshold = COLLECT_THRESHOLD.max(survivors.len() * 2);
+        state.allocations = 0;
+        state.stats = stats;
+
+        // Objects allocated while garbage was being dropped are tracked after the survivors
+        survivors.append(&mut state.tracked);
+        state.tracked = survivors;
+
+        collected
+    }
+
+    fn track(&self, tracked: Tracked) {
+        let should_collect: bool = {
+            let mut state = self.0.borrow_mut();
+            state.tracked.push(tracked);
+            state.allocations += 1;
+            state.allocations >= state.threshold
+        };
+
+        if should_collect {
+            self.collect();
+        }
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Finds the objects which are only reachable from other tracked objects.
+///
+/// Every reference between tracked objects is subtracted from the reference counts of the objects. Any object left
+/// with references is referred to from somewhere else, such as a variable or the VM stack, and so is every object
+/// reachable from it. The rest are garbage.
+fn find_garbage(nodes: &[Live]) -> Vec<bool> {
+    let index: HashMap<usize, usize> = nodes.iter()
+                                            .enumerate()
+                                            .map(|(i, node)| (node.address(), i))
+                                            .collect();
+
+    // The collection itself holds one reference to each node
+    let mut external: Vec<usize> = nodes.iter().map(|node| node.strong_count() - 1).collect();
+    let mut children: Vec<Vec<usize>> = Vec::with_capacity(nodes.len());
+
+    for node in nodes {
+        let mut edges: Vec<usize> = Vec::new();
+        node.trace(&mut edges);
+
+        let node_children: Vec<usize> = edges.iter().filter_map(|edge| index.get(edge).copied()).collect();
+
+        for child in &node_children {
+            external[*child] = external[*child].saturating_sub(1);
+        }
+
+        children.push(node_children);
+    }
+
+    let mut garbage: Vec<bool> = vec![true; nodes.len()];
+    let mut stack: Vec<usize> = (0..nodes.len()).filter(|i| external[*i] > 0).collect();
+
+    while let Some(node) = stack.pop() {
+        if garbage[node] {
+            garbage[node] = false;
+            stack.extend(&children[node]);
+        }
+    }
+
+    garbage
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Live> {
+        match self {
+            Self::Instance(object) => object.upgrade().map(Live::Instance),
+            Self::Class(class) => class.upgrade().map(Live::Class),
+            Self::List(list) => list.upgrade().map(Live::List),
+            Self::Map(map) => map.upgrade().map(Live::Map),
+            Self::Iterator(iterator) => iterator.upgrade().map(Live::Iterator),
+            Self::Generator(generator) => generator.upgrade().map(Live::Generator),
+            Self::Scope(scope) => scope.upgrade().map(Live::Scope),
+            Self::Closure(closure) => closure.upgrade().map(Live::Closure),
+            Self::Upvalue(upvalue) => upvalue.upgrade().map(Live::Upvalue)
+        }
+    }
+}
+
+impl Live {
+    fn address(&self) -> usize {
+        match self {
+            Self::Instance(object) => address(object),
+            Self::Class(class) => address(class),
+            Self::List(list) => address(list),
+            Self::Map(map) => address(map),
+            Self::Iterator(iterator) => address(iterator),
+            Self::Generator(generator) => address(generator),
+            Self::Scope(scope) => address(scope),
+            Self::Closure(closure) => address(closure),
+            Self::Upvalue(upvalue) => address(upvalue)
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Self::Instance(object) => Rc::strong_count(object),
+            Self::Class(class) => Rc::strong_count(class),
+            Self::List(list) => Rc::strong_count(list),
+            Self::Map(map) => Rc::strong_count(map),
+            Self::Iterator(iterator) => Rc::strong_count(iterator),
+            Self::Generator(generator) => Rc::strong_count(generator),
+            Self::Scope(scope) => Rc::strong_count(scope),
+            Self::Closure(closure) => Rc::strong_count(closure),
+            Self::Upvalue(upvalue) => Rc::strong_count(upvalue)
+        }
+    }
+
+    /// Adds the objects this object refers to. An object which is borrowed is in use, so it is not traced, which keeps
+    /// everything it refers to alive.
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match self {
+            Self::Instance(object) => if let Ok(object) = object.try_borrow() { object.trace(edges) },
+            Self::Class(class) => class.trace(edges),
+            Self::List(list) => if let Ok(list) = list.try_borrow() { list.trace(edges) },
+            Self::Map(map) => if let Ok(map) = map.try_borrow() { map.trace(edges) },
+            Self::Iterator(iterator) => if let Ok(iterator) = iterator.try_borrow() { iterator.trace(edges) },
+            Self::Generator(generator) => if let Ok(generator) = generator.try_borrow() { generator.trace(edges) },
+            Self::Scope(scope) => if let Ok(scope) = scope.try_borrow() { scope.trace(edges) },
+            Self::Closure(closure) => closure.trace(edges),
+            Self::Upvalue(upvalue) => if let Ok(upvalue) = upvalue.try_borrow() { upvalue.trace(edges) }
+        }
+    }
+
+    fn downgrade(&self) -> Tracked {
+        match self {
+            Self::Instance(object) => Tracked::Instance(Rc::downgrade(object)),
+            Self::Class(class) => Tracked::Class(Rc::downgrade(class)),
+            Self::List(list) => Tracked::List(Rc::downgrade(list)),
+            Self::Map(map) => Tracked::Map(Rc::downgrade(map)),
+            Self::Iterator(iterator) => Tracked::Iterator(Rc::downgrade(iterator)),
+            Self::Generator(generator) => Tracked::Generator(Rc::downgrade(generator)),
+            Self::Scope(scope) => Tracked::Scope(Rc::downgrade(scope)),
+            Self::Closure(closure) => Tracked::Closure(Rc::downgrade(closure)),
+            Self::Upvalue(upvalue) => Tracked::Upvalue(Rc::downgrade(upvalue))
+        }
+    }
+
+    /// Drops the references held by a garbage object, breaking the cycles it is part of. Iterators and generators are
+    /// left with nothing more to give, and classes only drop their static members. Closures cannot be changed, but any
+    /// other cycle through classes and closures passes through a scope or upvalue, so they are freed once those are
+    /// cleared.
+    fn clear(&self, stats: &mut GcStats) {
+        match self {
+            Self::Instance(object) => {
+                if let Ok(mut object) = object.try_borrow_mut() {
+                    object.fields.clear();
+                }
+
+                stats.instances += 1;
+            },
+            Self::Class(class) => {
+                class.clear_statics();
+                stats.classes += 1;
+            },
+            Self::List(list) => {
+                if let Ok(mut list) = list.try_borrow_mut() {
+                    list.items.clear();
+                }
+
+                stats.lists += 1;
+            },
+            Self::Map(map) => {
+                if let Ok(mut map) = map.try_borrow_mut() {
+                    map.clear();
+                }
+
+                stats.maps += 1;
+            },
+            Self::Iterator(iterator) => {
+                if let Ok(mut iterator) = iterator.try_borrow_mut() {
+                    *iterator = LoxIterator::Values(Vec::new().into_iter());
+                }
+
+                stats.iterators += 1;
+            },
+            Self::Generator(generator) => {
+                if let Ok(mut generator) = generator.try_borrow_mut() {
+                    generator.clear();
+                }
+
+                stats.generators += 1;
+            },
+            Self::Scope(scope) => {
+                if let Ok(mut scope) = scope.try_borrow_mut() {
+                    *scope = Scope::default();
+                }
+
+                stats.scopes += 1;
+            },
+            Self::Closure(_) => stats.closures += 1,
+            Self::Upvalue(upvalue) => {
+                if let Ok(mut upvalue) = upvalue.try_borrow_mut() {
+                    *upvalue = Upvalue::Closed(Value::Nil());
+                }
+
+                stats.upvalues += 1;
+            }
+        }
+    }
+}
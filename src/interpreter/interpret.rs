@@ -1,481 +1,1116 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
+use std::io::{self, Write};
 use std::rc::Rc;
 
 use crate::LoxError;
-use crate::interpreter::environment::Environment;
-use crate::types::values::object::LoxObject;
+use crate::interpreter::{environment::Environment, gc::Trace, modules, operators};
+use crate::types::values::{generator::{GeneratorState, LoxGenerator}, iterator::LoxIterator, list::LoxList, map::LoxMap, object::LoxObject};
 use crate::types::values::{callable::LoxCallable, Value, class::LoxClass};
-use crate::types::{{expr::Expr, statement::Statement, token::Token, token_type::TokenType::*}};
+use crate::types::{{expr::Expr, statement::Statement, token::Token, token_type::TokenType::{self, *}}};
 
-/// Generates codes for calculations
-macro_rules! calculate {
-    ($left:ident, $op_token:ident, $right:ident, $env:ident, $op:tt) => {
-        match $left {
-            Value::Int(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Int(l_value $op r_value)),
-                Value::Float(r_value) => Ok(Value::Float(l_value as f64 $op r_value)),
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
-            },
-            Value::Float(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Float(l_value $op r_value as f64 )),
-                Value::Float(r_value) => Ok(Value::Float(l_value $op r_value)),
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+/// A tree-walking interpreter for Lox programs.
+///
+/// Print statements write to the interpreter's output sink, and errors are reported to its diagnostics sink. These
+/// default to stdout and stderr.
+///
+/// Every call is interpreted on the Rust stack, so the interpreter limits how deeply calls can be nested.
+pub struct Interpreter {
+    output: Rc<RefCell<dyn Write>>,
+    diagnostics: Box<dyn Write>,
+    call_depth: usize,
+    max_call_depth: usize,
+    // Where the generator being suspended or resumed is inside its body, from the innermost statement outwards
+    resume_path: Vec<Resume>,
+    evaluation: Evaluation
+}
+
+/// How control leaves a statement.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Flow {
+    /// Control continues to the next statement.
+    Normal,
+    /// The enclosing function returns a value, which may be nil.
+    Return(Value),
+    Break,
+    Continue,
+    /// The enclosing generator suspends, giving a value to whatever resumed it.
+    Yield(Value)
+}
+
+/// A statement a generator suspended inside of, and where in the statement it continues once resumed.
+///
+/// When a generator yields, each statement it leaves records where it was, and keeps the scopes it pushed. Once the
+/// generator is resumed, each statement takes its record back and carries on from there instead of starting over.
+#[derive(Debug)]
+pub(crate) enum Resume {
+    /// The statement at an index of a list of statements
+    Statement(usize),
+    /// The branch of an if statement, by whether the condition was true
+    Branch(bool),
+    /// The body of a while loop
+    Loop,
+    /// The body of a for-in loop, with the iterator the loop is using
+    ForIn(Value),
+    /// The body of a try statement
+    Try,
+    /// The catch clause of a try statement
+    Catch,
+    /// The finally block of a try statement, with the outcome it was entered with
+    Finally(Result<Flow, LoxError>),
+    /// The increment of a for loop
+    Increment,
+    /// The expression a yield is part of, with the values of the expressions it finished evaluating before the yield,
+    /// and the value the generator is resumed with
+    Yield(Vec<(usize, Value)>, Value)
+}
+
+/// How far the generator being run has evaluated the expression it is in.
+///
+/// While a generator runs, the value of every expression it finishes is recorded, along with how deeply it is nested,
+/// until the outermost expression finishes. When a yield suspends the generator part way through an expression, the
+/// recorded values are kept. Once resumed, the expression is evaluated again, taking the recorded values back instead
+/// of evaluating those parts again, until it reaches the yield.
+#[derive(Default)]
+struct Evaluation {
+    recording: bool,
+    // Set while a recorded expression is handed back to be evaluated
+    evaluating: bool,
+    depth: usize,
+    finished: Vec<(usize, Value)>,
+    // The recorded values a resumed generator takes back, with the first one last
+    replay: Vec<(usize, Value)>,
+    sent: Option<Value>,
+    yielded: Option<Value>
+}
+
+impl Evaluation {
+    /// Takes back the recorded value of the expression about to be evaluated, if it has one. It stays recorded, in
+    /// case the generator yields again in the same expression.
+    fn replayed(&mut self) -> Option<Value> {
+        match self.replay.last() {
+            Some((depth, _)) if *depth == self.depth => {
+                let (depth, value): (usize, Value) = self.replay.pop()?;
+                self.finished.push((depth, value.clone()));
+                Some(value)
             },
-            Value::Identifier(name) => interpret_expr(build_binary_expr($env.get(&*name)?, $op_token, $right), $env),
-            _ => Err(LoxError::ValueError($left, String::from("Not a number.")))
+            _ => None
+        }
+    }
+
+    /// Records the value of an expression, dropping the values of the expressions inside it. They are kept if the
+    /// generator is suspending.
+    fn record(&mut self, mark: usize, result: &Result<Value, LoxError>) {
+        if self.yielded.is_none() {
+            self.finished.truncate(mark);
+
+            if let Ok(value) = result && self.depth > 0 {
+                self.finished.push((self.depth, value.clone()));
+            }
         }
     }
 }
 
-/// Generates code for comparisons
-macro_rules! compare {
-    ($left:ident, $op_token:ident, $right:ident, $env:ident, $op:tt) => {
-        match $left {
-            Value::Identifier(name) => interpret_expr(build_binary_expr($env.get(&*name)?, $op_token, $right), $env),
-            Value::Int(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value)),
-                Value::Float(r_value) =>  {
-                    let l_value = l_value as f64;
-                    Ok(Value::Bool(l_value $op r_value))
-                }
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+// Errors kept for finally blocks are left out, which keeps whatever they refer to alive.
+impl Trace for Resume {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match self {
+            Self::ForIn(value) => value.trace(edges),
+            Self::Yield(finished, sent) => {
+                finished.iter().for_each(|(_, value)| value.trace(edges));
+                sent.trace(edges);
             },
-            Value::Float(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value as f64)),
-                Value::Float(r_value) => Ok(Value::Bool(l_value $op r_value)),
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
-            },
-            Value::None() => {
-                Err(LoxError::ValueError($left, String::from("You cannot compare a value to a keyword.")))
-            },
-            _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+            Self::Finally(Ok(Flow::Return(value) | Flow::Yield(value))) => value.trace(edges),
+            _ => ()
         }
     }
 }
 
-/// Generates code for determining equality
-macro_rules! equal {
-    ($left:ident, $op_token:ident, $right:ident, $env:ident, $op:tt) => {
-        match $left {
-            Value::Identifier(name) => interpret_expr(build_binary_expr($env.get(&*name)?, $op_token, $right), $env),
-            Value::Str(l_value) => {
-                if let Value::Str(r_value) = $right {
-                    return Ok(Value::Bool(l_value $op r_value))
+/// The default limit on the depth of nested calls.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// How much stack a call needs to have left before it runs, and how much is added when it does not. Unoptimized
+/// builds use a few hundred kilobytes of stack for each Lox call, and nearly 200 kilobytes more for each level of
+/// nesting in the expressions it evaluates before the next call.
+const STACK_RED_ZONE: usize = 2 * 1024 * 1024;
+const STACK_GROWTH: usize = 16 * 1024 * 1024;
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            output: Rc::new(RefCell::new(io::stdout())),
+            diagnostics: Box::new(io::stderr()),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            resume_path: Vec::new(),
+            evaluation: Evaluation::default()
+        }
+    }
+
+    /// Sets the maximum depth of nested calls. Going deeper raises a stack overflow error.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    /// Sets the sink that print statements write to.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Rc::new(RefCell::new(output));
+    }
+
+    /// Returns the sink that print statements write to, so it can be shared with the bytecode VM.
+    pub fn output(&self) -> Rc<RefCell<dyn Write>> {
+        self.output.clone()
+    }
+
+    /// Sets the sink that errors are reported to.
+    pub fn set_diagnostics(&mut self, diagnostics: impl Write + 'static) {
+        self.diagnostics = Box::new(diagnostics);
+    }
+
+    /// Returns the sink that errors are reported to.
+    pub fn diagnostics(&mut self) -> &mut dyn Write {
+        &mut *self.diagnostics
+    }
+
+    /// Interprets a list of statements, returning the value of the return statement which stopped them, or nil.
+    pub fn interpret(&mut self, program: Vec<Statement>, env: &mut Environment) -> Result<Value, LoxError> {
+        match self.interpret_statements(program, env)? {
+            Flow::Return(value) => Ok(value),
+            // The parser only allows break and continue inside loops, which stop them, and yields inside generators
+            Flow::Normal | Flow::Break | Flow::Continue | Flow::Yield(_) => Ok(Value::Nil())
+        }
+    }
+
+    /// Interprets statements until one of them moves control elsewhere.
+    fn interpret_statements(&mut self, statements: Vec<Statement>, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator skips the statements which ran before it yielded
+        let start: usize = match self.resume_path.pop() {
+            Some(Resume::Statement(index)) => index,
+            _ => 0
+        };
+
+        for (index, stmt) in statements.into_iter().enumerate().skip(start) {
+            let flow: Flow = self.interpret_statement(stmt, env)?;
+
+            if let Flow::Yield(_) = flow {
+                self.resume_path.push(Resume::Statement(index));
+            }
+
+            if flow != Flow::Normal {
+                return Ok(flow)
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Interprets a statement. A statement which yields part way through an expression suspends the generator.
+    fn interpret_statement(&mut self, stmt: Statement, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator carries on with the expression it yielded in
+        self.resume_expression();
+        self.execute_statement(stmt, env).or_else(|error| self.suspend(error))
+    }
+
+    /// Matches and interprets each type of statement
+    fn execute_statement(&mut self, stmt: Statement, env: &mut Environment) -> Result<Flow, LoxError> {
+        match stmt {
+            Statement::Block(statements) => self.interpret_block(*statements, env),
+            Statement::Break(_) => Ok(Flow::Break),
+            Statement::Class(name, superclass, methods, accessors, statics, doc) => {
+                let class: Rc<LoxClass> = self.interpret_class(name, superclass, *methods, *accessors, doc, env)?;
+                self.interpret_statics(&class, *statics, env)?;
+                Ok(Flow::Normal)
+            },
+            Statement::Continue(_) => Ok(Flow::Continue),
+            Statement::FunDeclaration(name, args, body, doc) => {
+                interpret_closure(name, args, body, doc, env);
+                Ok(Flow::Normal)
+            },
+            Statement::Expression(expr) => {
+                self.interpret_expr(expr, env)?;
+                Ok(Flow::Normal)
+            },
+            Statement::ForIn(name, iterable, body) => {
+                // A resumed generator carries on with the iterator of the loop it yielded in
+                match self.resume_path.pop() {
+                    Some(Resume::ForIn(iterator)) => self.interpret_for_in(name, iterator, true, *body, env),
+                    _ => {
+                        let iterable: Value = self.interpret_expr(iterable, env)?;
+                        let iterator: Value = self.interpret_iterator(iterable, env)?;
+                        self.interpret_for_in(name, iterator, false, *body, env)
+                    }
                 }
+            },
+            Statement::FromImport(path, names) => {
+                let module: Value = self.import(&path, env)?;
 
-                Ok(Value::Bool(false))
+                for name in names {
+                    let value: Value = get_property(module.clone(), &name)?;
+                    env.define(name.lexeme, value);
+                }
+
+                Ok(Flow::Normal)
             },
-            Value::Int(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value)),
-                Value::Float(r_value) => Ok(Value::Bool(l_value as f64 $op r_value)),
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Ok(Value::Bool(false))
+            Statement::Generator(body) => {
+                let generator: LoxGenerator = LoxGenerator::new(GeneratorState::Walking(body, env.clone(), Vec::new()));
+                Ok(Flow::Return(Value::Generator(env.heap().generator(generator))))
             },
-            Value::Float(l_value) => match $right {
-                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value as f64)),
-                Value::Float(r_value) => Ok(Value::Bool(l_value $op r_value)),
-                Value::Identifier(name) => interpret_expr(build_binary_expr($left, $op_token, $env.get(&*name)?), $env),
-                _ => Ok(Value::Bool(false))
+            Statement::If(cond, then, els) => self.interpret_if(cond, then, els, env),
+            Statement::Import(path, name) => {
+                let module: Value = self.import(&path, env)?;
+                env.define(name.lexeme, module);
+                Ok(Flow::Normal)
             },
-            Value::Bool(l_value) => {
-                if let Value::Bool(r_value) = $right {
-                    return Ok(Value::Bool(l_value $op r_value))
+            Statement::Print(print_expr) => {
+                let value: Value = self.interpret_expr(print_expr, env)?;
+                let string: String = self.interpret_str(&value, env)?;
+
+                match writeln!(self.output.borrow_mut(), "{string}") {
+                    Ok(_) => Ok(Flow::Normal),
+                    Err(err) => Err(LoxError::ValueError(value, format!("Value could not be printed: {err}")))
                 }
+            },
+            Statement::Return(_, return_expr) => Ok(Flow::Return(self.interpret_expr(return_expr, env)?)),
+            Statement::Throw(_, expr) => Err(LoxError::Thrown(self.interpret_expr(expr, env)?)),
+            Statement::Try(body, catch, finally) => self.interpret_try(*body, catch, finally, env),
+            Statement::Var(name, identifier) => {
+                self.interpret_declaration(name, identifier, env)?;
+                Ok(Flow::Normal)
+            },
+            Statement::While(cond, body, increment) => self.interpret_while(cond, *body, increment, env)
+        }
+    }
+
+    /// Resumes a generator until it yields or returns, returning the value it gave. The yield it stopped at evaluates
+    /// to the value it is resumed with.
+    pub(crate) fn resume(&mut self, generator: &Rc<RefCell<LoxGenerator>>, sent: Value) -> Result<Value, LoxError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(LoxError::StackOverflow(String::from("generator")));
+        }
 
-                Ok(Value::Bool(false))
+        let Some(state) = LoxGenerator::start(generator)? else { return Ok(Value::Nil()) };
+        let GeneratorState::Walking(body, mut env, mut resume) = state else {
+            return Err(LoxError::ValueError(Value::Generator(generator.clone()), String::from("Generator can only be resumed by the bytecode VM.")));
+        };
+
+        // The yield is the innermost part of where the generator stopped
+        if let Some(Resume::Yield(_, value)) = resume.first_mut() {
+            *value = sent;
+        }
+
+        self.call_depth += 1;
+        self.resume_path = resume;
+        let evaluation: Evaluation = std::mem::replace(&mut self.evaluation, Evaluation { recording: true, ..Evaluation::default() });
+        let result: Result<Flow, LoxError> = grow_stack(|| self.interpret_statements((*body).clone(), &mut env));
+        self.evaluation = evaluation;
+        let resume: Vec<Resume> = std::mem::take(&mut self.resume_path);
+        self.call_depth -= 1;
+
+        let value: Result<Value, LoxError> = match result {
+            Ok(Flow::Yield(value)) => {
+                generator.borrow_mut().suspend(GeneratorState::Walking(body, env, resume));
+                Ok(value)
             },
-            Value::Nil() => Ok(Value::Bool($left $op $right)),
-            _ => {
-                Err(LoxError::ValueError($left, String::from("Value is not comparable.")))
-            }
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(_) => Ok(Value::Nil()),
+            Err(error) => Err(error)
+        };
+
+        generator.borrow_mut().finish();
+        value
+    }
+
+    /// Takes back the values a resumed generator recorded in the expression it yielded in, if it yielded in this one.
+    fn resume_expression(&mut self) {
+        if let Some(Resume::Yield(..)) = self.resume_path.last() && let Some(Resume::Yield(finished, sent)) = self.resume_path.pop() {
+            self.evaluation.replay = finished.into_iter().rev().collect();
+            self.evaluation.sent = Some(sent);
         }
     }
-}
 
-/// Interprets a list of statements
-pub fn interpret(program: Vec<Statement>, env: &mut Environment) -> Result<Value, LoxError> {
-    for stmt in program {
-        let result = interpret_statement(stmt, env)?;
+    /// Suspends the generator if an error comes from a yield stopping part way through an expression, keeping the
+    /// values it recorded. Any other error is passed on.
+    fn suspend(&mut self, error: LoxError) -> Result<Flow, LoxError> {
+        match self.evaluation.yielded.take() {
+            Some(value) => {
+                self.resume_path.push(Resume::Yield(std::mem::take(&mut self.evaluation.finished), Value::Nil()));
+                Ok(Flow::Yield(value))
+            },
+            None => Err(error)
+        }
+    }
 
-        if result != Value::Nil() {
-            return Ok(result)
+    /// Interprets a block in a new scope.
+    fn interpret_block(&mut self, statements: Vec<Statement>, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator is already inside the block's scope, which it keeps while it is suspended
+        if self.resume_path.is_empty() {
+            env.push_scope();
         }
+
+        let result: Result<Flow, LoxError> = self.interpret_statements(statements, env);
+
+        if !matches!(result, Ok(Flow::Yield(_))) {
+            env.pop_scope();
+        }
+
+        result
     }
 
-    Ok(Value::Nil())
-}
+    /// Imports a module, interpreting it the first time it is imported.
+    fn import(&mut self, path: &Token, env: &Environment) -> Result<Value, LoxError> {
+        modules::import(&path.literal.to_string(), env, |statements, module_env| {
+            self.interpret(statements, module_env).map(|_| ())
+        })
+    }
 
-/// Matches and interprets each type of statement
-fn interpret_statement(stmt: Statement, env: &mut Environment) -> Result<Value, LoxError> {
-    match stmt {
-        Statement::Block(statements) => interpret_block(statements, env),
-        Statement::Class(name, superclass, methods) => {
-            interpret_class(name, superclass, *methods, env)?;
-            Ok(Value::Nil())
-        },
-        Statement::FunDeclaration(name, args, body) => {
-            interpret_closure(name, args, body, env)?;
-            Ok(Value::Nil())
-        },
-        Statement::Expression(expr) => {
-            interpret_expr(expr, env)?;
-            Ok(Value::Nil())
-        },
-        Statement::If(cond, then, els) => interpret_if(cond, then, els, env),
-        Statement::Print(print_expr) => {
-            println!("{}", interpret_expr(print_expr, env)?);
-            Ok(Value::Nil())
-        },
-        Statement::Return(return_expr) => interpret_expr(return_expr, env),
-        Statement::Var(name, identifier) => {
-            interpret_declaration(name, identifier, env)?;
-            Ok(Value::Nil())
-        },
-        Statement::While(cond, body) => interpret_while(cond, *body, env)
+    /// Evaluates a single expression in an environment.
+    pub fn evaluate(&mut self, expr: Expr, env: &mut Environment) -> Result<Value, LoxError> {
+        self.interpret_expr(expr, env)
     }
-}
 
-/// Interprets a block in a new block environment.
-fn interpret_block(statements: Box<Vec<Statement>>, env: &mut Environment) -> Result<Value, LoxError> {
-    let mut block_env: Environment = Environment::get_block_env(env);
-    let result: Value = interpret(*statements, &mut block_env)?;
+    /// Interprets the value of an expression.
+    fn interpret_expr(&mut self, ast: Expr, env: &mut Environment) -> Result<Value, LoxError> {
+        // While a generator runs, each expression is recorded, which hands it back here to be evaluated
+        if self.evaluation.recording && !std::mem::take(&mut self.evaluation.evaluating) {
+            return self.record_expr(ast, env);
+        }
 
-    // Add new assignments to current environment
-    env.add_assignments(&mut block_env);
+        match ast {
+            Expr::Literal(value) => Ok(value),
+            Expr::Grouping(expr) => self.interpret_expr(*expr, env),
+            Expr::Unary(op, expr) => {
+                let expr_value: Value = resolve_identifier(self.interpret_expr(*expr, env)?, env)?;
 
-    Ok(result)
-}
+                match operators::unary_method(op.token_type).and_then(|name| find_method(&expr_value, name)) {
+                    Some(method) => self.call_callable(method, Vec::new(), env),
+                    None => operators::unary(op.token_type, expr_value)
+                }
+            },
+            Expr::Binary(left, op, right) => {
+                let left: Value = resolve_identifier(self.interpret_expr(*left, env)?, env)?;
+                let right: Value = resolve_identifier(self.interpret_expr(*right, env)?, env)?;
+                self.interpret_binary(left, op.token_type, right, env)
+            },
+            Expr::Logical(left, op, right) => {
+                match op.token_type {
+                    And | Or => {
+                        let left_value: Value = resolve_identifier(self.interpret_expr(*left, env)?, env)?;
+                        let right_value: Value = resolve_identifier(self.interpret_expr(*right, env)?, env)?;
+                        Ok(Value::Bool(operators::logical(left_value, op.token_type, right_value)?))
+                    },
+                    _ => Err(LoxError::CompilerBug(Statement::Expression(Expr::Logical(left.clone(), op, right.clone())),
+                                                   String::from("Invalid operator used in Expr::Logical")))
+                }
+            }
+            Expr::Variable(name, depth) | Expr::This(name, depth) => env.get_at(depth, &name),
+            Expr::Assign(name, exp, depth) => {
+                let value: Value = self.interpret_expr(*exp, env)?;
+                env.assign_at(depth, name, value)?;
+                Ok(Value::Nil())
+            },
+            Expr::Call(name, args) => self.interpret_call(name, *args, env),
+            Expr::Get(object, property) => {
+                let object: Value = self.interpret_expr(*object, env)?;
+                self.interpret_get(object, &property, env)
+            },
+            Expr::Set(object, property, value) => {
+                let object: Value = self.interpret_expr(*object, env)?;
+                let value: Value = self.interpret_expr(*value, env)?;
+                self.interpret_set(object, property, value, env)?;
+                Ok(Value::Nil())
+            },
+            Expr::UpdateProperty(object, property, op, value) => {
+                let object: Value = self.interpret_expr(*object, env)?;
+                let current: Value = self.interpret_step(|this| this.interpret_get(object.clone(), &property, env))?;
+                let value: Value = self.interpret_expr(*value, env)?;
+                let value: Value = self.interpret_binary(current, op.token_type, value, env)?;
+                self.interpret_set(object, property, value, env)?;
+                Ok(Value::Nil())
+            },
+            Expr::List(elements) => {
+                let mut items: Vec<Value> = Vec::new();
 
-/// Interprets the value of an expression.
-fn interpret_expr(ast: Expr, env: &mut Environment) -> Result<Value, LoxError> {
-    match ast {
-        Expr::Literal(value) => Ok(value),
-        Expr::Grouping(expr) => interpret_expr(*expr, env),
-        Expr::Unary(op, expr) => {
-            let expr_value: Value = interpret_expr(*expr, env)?;
-
-            match op.token_type {
-                Minus => {
-                    match expr_value {
-                        Value::Float(n) => Ok(Value::Float(-n)),
-                        Value::Int(n) => Ok(Value::Int(-n)),
-                        Value::Identifier(name) => interpret_expr(Expr::Unary(op, Box::new(Expr::Literal(env.get(&*name)?))), env), // Recurses with a new expression containing the literal instead of the identifier
-                        _ => Err(LoxError::ValueError(expr_value, String::from("Negation operator cannot be used here.")))
-                    }
+                for element in *elements {
+                    items.push(self.interpret_expr(element, env)?);
                 }
 
-                Not => {
-                    match expr_value {
-                        Value::Bool(b) => Ok(Value::Bool(!b)),
-                        Value::Identifier(name) => interpret_expr(Expr::Unary(op, Box::new(Expr::Literal(env.get(&*name)?))), env),
-                        _ => Err(LoxError::ValueError(expr_value, String::from("Not operator cannot be used here.")))
-                    }
+                Ok(Value::List(env.heap().list(LoxList::new(items))))
+            },
+            Expr::Lambda(name, params, body) => Ok(Value::Callable(LoxCallable::Closure(name.lexeme, params, body, env.clone(), false, None))),
+            Expr::Map(entries) => {
+                let mut map: LoxMap = LoxMap::new();
+
+                for (key, value) in *entries {
+                    let key: Value = self.interpret_expr(key, env)?;
+                    let value: Value = self.interpret_expr(value, env)?;
+                    map.set(key, value)?;
                 }
 
-                _ => Err(LoxError::CompilerBug(Statement::Expression(Expr::Literal(expr_value)), format!("Unary operator was created with invalid token type {}", op.token_type)))
-            }
-        },
-        Expr::Binary(left, op, right) => {
-            let left: Value = interpret_expr(*left, env)?;
-            let right: Value = interpret_expr(*right, env)?;
-
-            match op.token_type {
-                Minus => calculate!(left, op, right, env, -),
-                // Plus does not use the calculate macro, as it can be used for calculation and concatenation
-                Plus => match left {
-                    Value::Int(l_value) => match right {
-                        Value::Int(r_value) => Ok(Value::Int(l_value + r_value)),
-                        Value::Float(r_value) => Ok(Value::Float(l_value as f64 + r_value)),
-                        Value::Identifier(name) => interpret_expr(build_binary_expr(left, op, env.get(&*name)?), env),
-                        Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                        _ => Err(LoxError::ValueError(right, String::from("Not a number.")))
+                Ok(Value::Map(env.heap().map(map)))
+            },
+            Expr::Stringify(expr) => {
+                let value: Value = resolve_identifier(self.interpret_expr(*expr, env)?, env)?;
+                Ok(Value::Str(self.interpret_str(&value, env)?))
+            },
+            Expr::Index(collection, index) => {
+                let collection: Value = self.interpret_expr(*collection, env)?;
+                let index: Value = self.interpret_expr(*index, env)?;
+                get_index(collection, index)
+            },
+            Expr::SetIndex(collection, index, value) => {
+                let collection: Value = self.interpret_expr(*collection, env)?;
+                let index: Value = self.interpret_expr(*index, env)?;
+                let value: Value = self.interpret_expr(*value, env)?;
+                set_index(collection, index, value)?;
+                Ok(Value::Nil())
+            },
+            Expr::UpdateIndex(collection, index, op, value) => {
+                let collection: Value = self.interpret_expr(*collection, env)?;
+                let index: Value = self.interpret_expr(*index, env)?;
+                let current: Value = self.interpret_step(|_| get_index(collection.clone(), index.clone()))?;
+                let value: Value = self.interpret_expr(*value, env)?;
+                let value: Value = self.interpret_binary(current, op.token_type, value, env)?;
+                set_index(collection, index, value)?;
+                Ok(Value::Nil())
+            },
+            Expr::Super(name, method, depth) => {
+                let name_value: Value = env.get_at(depth, &name)?;
+
+                match name_value {
+                    Value::Class(class) => {
+                        let method_result: Option<LoxCallable> = class.find_method(&method.lexeme);
+
+                        match method_result {
+                            Some(method) => {
+                                // Get this, which is always declared in the scope inside of super
+                                let this_token: Token = Token::new(This, String::from("this"), Value::None(), name.line);
+                                let this_value: Value = env.get_at(depth.map(|depth| depth - 1), &this_token)?;
+                                let this_result: Result<Rc<RefCell<LoxObject>>, Value> = this_value.into_instance();
+
+                                match this_result {
+                                    Ok(this) => Ok(Value::Callable(method.bind(this))),
+                                    Err(value) => Err(LoxError::ValueError(value, String::from("this is a defined variable in this scope, instead of a keyword.")))
+                                }
+                            },
+                            None => Err(LoxError::NameError(name.lexeme, String::from("Name does not correspond to a function.")))
+                        }
                     },
-                    Value::Float(l_value) => match right {
-                        Value::Int(r_value) => Ok(Value::Float(l_value + r_value as f64 )),
-                        Value::Float(r_value) => Ok(Value::Float(l_value + r_value)),
-                        Value::Identifier(name) => interpret_expr(build_binary_expr(left, op, env.get(&*name)?), env),
-                        Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                        _ => Err(LoxError::ValueError(right, String::from("Not a number.")))
+                    _ => Err(LoxError::ValueError(name_value, String::from("'super' does not refer to a class.")))
+                }
+            },
+            // A yield stops the statement it is in, which suspends the generator. Once resumed, the yield which stopped
+            // takes back its recorded operand, so it is the first yield to finish its operand, and gives the value sent
+            Expr::Yield(keyword, value) => {
+                let value: Value = self.interpret_expr(*value, env)?;
+
+                if let Some(sent) = self.evaluation.sent.take() {
+                    return Ok(sent);
+                }
+
+                if self.evaluation.recording {
+                    self.evaluation.yielded = Some(value);
+                }
+
+                Err(LoxError::RuntimeError(Expr::Yield(keyword, Box::new(Expr::Literal(Value::Nil()))), String::from("A yield can only suspend a generator.")))
+            }
+        }
+    }
+
+    /// Interprets an expression while a generator runs, recording its value. A resumed generator takes the values it
+    /// recorded back instead.
+    fn record_expr(&mut self, ast: Expr, env: &mut Environment) -> Result<Value, LoxError> {
+        if let Some(value) = self.evaluation.replayed() {
+            return Ok(value);
+        }
+
+        let mark: usize = self.evaluation.finished.len();
+        self.evaluation.depth += 1;
+        self.evaluation.evaluating = true;
+        let result: Result<Value, LoxError> = self.interpret_expr(ast, env);
+        self.evaluation.depth -= 1;
+        self.evaluation.record(mark, &result);
+
+        result
+    }
+
+    /// Runs part of an expression which a resumed generator should not repeat, recording its value as if it were an
+    /// expression inside it.
+    fn interpret_step(&mut self, step: impl FnOnce(&mut Self) -> Result<Value, LoxError>) -> Result<Value, LoxError> {
+        if let Some(value) = self.evaluation.replayed() {
+            return Ok(value);
+        }
+
+        let mark: usize = self.evaluation.finished.len();
+        let result: Result<Value, LoxError> = step(self);
+        self.evaluation.record(mark, &result);
+
+        result
+    }
+
+    /// Interprets an if statement in a new block environment.
+    fn interpret_if(&mut self, cond: Expr, then: Box<Statement>, els: Box<Option<Statement>>, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator continues in the branch it yielded in, without checking the condition again
+        let truth: bool = match self.resume_path.pop() {
+            Some(Resume::Branch(truth)) => truth,
+            _ => get_value_truth(self.interpret_expr(cond, env)?, env)?
+        };
+
+        let flow: Flow = if truth {
+            self.interpret_statement(*then, env)?
+        }
+
+        else {
+            match *els {
+                None => Flow::Normal,
+                Some(else_case) => self.interpret_statement(else_case, env)?
+            }
+        };
+
+        if let Flow::Yield(_) = flow {
+            self.resume_path.push(Resume::Branch(truth));
+        }
+
+        Ok(flow)
+    }
+
+    /// Interprets a declaration and adds it to the current environment.
+    fn interpret_declaration(&mut self, name: Token, identifier: Option<Expr>, env: &mut Environment) -> Result<(), LoxError> {
+        match identifier {
+            None => {
+                env.define(name.lexeme, Value::Nil());
+                Ok(())
+            },
+            Some(exp) => {
+                match self.interpret_expr(exp, env) {
+                    Ok(value) => {
+                        env.define(name.lexeme, value);
+                        Ok(())
                     },
-                    Value::Identifier(name) => interpret_expr(build_binary_expr(env.get(&*name)?, op, right), env),
-                    Value::Bool(l_value) => {
-                        match right {
-                            Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                            _ => Err(LoxError::ValueError(right, String::from("Not a string.")))
-                        }
-                    }
-                    Value::Str(ref l_value) => {
-                        match right {
-                            Value::Int(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                            Value::Float(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                            Value::Identifier(name) => interpret_expr(build_binary_expr(left, op, env.get(&*name)?), env),
-                            Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                            Value::Bool(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
-                            _ => Err(LoxError::ValueError(right, String::from("Value cannot be concatenated to a string.")))
-                        }
+                    Err(error) => Err(error)
+                }
+            }
+        }
+    }
+
+    /// Interprets a while loop in a block environment. The increment of a for loop runs after every iteration.
+    fn interpret_while(&mut self, condition: Expr, body: Statement, increment: Option<Expr>, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator continues inside the body or the increment, without checking the condition first
+        let resumed: Option<Resume> = self.resume_path.pop();
+        let mut in_body: bool = matches!(resumed, Some(Resume::Loop));
+        let mut in_increment: bool = matches!(resumed, Some(Resume::Increment));
+
+        if in_increment {
+            self.resume_expression();
+        }
+
+        while in_body || in_increment || get_value_truth(self.interpret_expr(condition.clone(), env)?, env)? {
+            in_body = false;
+
+            if !std::mem::take(&mut in_increment) {
+                match self.interpret_statement(body.clone(), env)? {
+                    Flow::Break => break,
+                    Flow::Continue | Flow::Normal => (),
+                    Flow::Yield(value) => {
+                        self.resume_path.push(Resume::Loop);
+                        return Ok(Flow::Yield(value));
                     },
-                    _ => Err(LoxError::ValueError(left, String::from("Not a number.")))
-                },
-                // TODO: Handle divisions by zero
-                Slash => calculate!(left, op, right, env, /),
-                Asterisk => calculate!(left, op, right, env, *),
-                Mod => calculate!(left, op, right, env, %),
-                EqualEqual => equal!(left, op, right, env, ==),
-                Greater => compare!(left, op, right, env, >),
-                GreaterEqual => compare!(left, op, right, env, >=),
-                Less => compare!(left, op, right, env, <),
-                NotEqual => equal!(left, op, right, env, !=),
-                LessEqual => compare!(left, op, right, env, <=),
-                _ => Err(LoxError::ValueError(left, String::from("Does not have an interpretable value.")))
+                    flow => return Ok(flow)
+                }
             }
-        },
-        Expr::Logical(left, op, right) => {
-            match op.token_type {
-                And => {
-                    let left_value: Value = interpret_expr(*left, env)?;
-                    let right_value: Value = interpret_expr(*right, env)?;
-                    Ok(Value::Bool(get_value_truth(left_value, env)? && get_value_truth(right_value, env)?))
-                },
-                Or => {
-                    let left_value: Value = interpret_expr(*left, env)?;
-                    let right_value: Value = interpret_expr(*right, env)?;
-                    Ok(Value::Bool(get_value_truth(left_value, env)? || get_value_truth(right_value, env)?))
-                },
-                _ => Err(LoxError::CompilerBug(Statement::Expression(Expr::Logical(left.clone(), op, right.clone())),
-                                               String::from("Invalid operator used in Expr::Logical")))
+
+            if let Some(increment) = &increment && let Err(error) = self.interpret_expr(increment.clone(), env) {
+                let flow: Flow = self.suspend(error)?;
+                self.resume_path.push(Resume::Increment);
+                return Ok(flow);
             }
         }
-        Expr::Variable(name) | Expr::This(name) => env.get(&name),
-        Expr::Assign(name, exp) => {
-            let value: Value = interpret_expr(*exp, env)?;
-            env.assign(name, value)?;
-            Ok(Value::Nil())
-        },
-        Expr::Call(name, args) => interpret_call(name, *args, env),
-        Expr::Get(object, property) => {
-            let object: Rc<RefCell<LoxObject>> = get_object(*object, env)?;
-            get_from_ref(object, &property)
-        },
-        Expr::Set(object, property, value) => {
-            let object: Rc<RefCell<LoxObject>> = get_object(*object, env)?;
-            let mut object: RefMut<LoxObject> = object.borrow_mut();
-            object.set(property, interpret_expr(*value, env)?);
-            Ok(Value::Nil())
-        },
-        Expr::Super(name, method) => {
-            let name_value: Value = env.get(&name)?;
-
-            match name_value {
-                Value::Class(class) => {
-                    let method_result: Option<LoxCallable> = class.find_method(&method.lexeme);
-
-                    match method_result {
-                        Some(method) => {
-                            // Get this
-                            let this_value: Value = env.get_by_str("this")?;
-                            let this_result: Result<Rc<RefCell<LoxObject>>, Value> = this_value.into_instance();
-
-                            match this_result {
-                                Ok(this) => Ok(Value::Callable(method.bind(this))),
-                                Err(value) => Err(LoxError::ValueError(value, String::from("this is a defined variable in this scope, instead of a keyword.")))
-                            }
-                        },
-                        None => Err(LoxError::NameError(name.lexeme, String::from("Name does not correspond to a function.")))
-                    }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Starts iterating over a value. Objects give their iterator through an `iterator()` method.
+    fn interpret_iterator(&mut self, iterable: Value, env: &Environment) -> Result<Value, LoxError> {
+        let iterator: Value = match iterable {
+            Value::Instance(_) | Value::Host(_) => self.call_callable(iteration_method(&iterable, "iterator")?, Vec::new(), env)?,
+            _ => iterable
+        };
+
+        match iterator {
+            Value::Instance(_) | Value::Host(_) => Ok(iterator),
+            _ => LoxIterator::over(iterator, env.heap())
+        }
+    }
+
+    /// Interprets a loop over the items of an iterator, binding each item to the loop variable in a scope of its own.
+    /// A resumed generator is already inside the body, and the scope of its item.
+    fn interpret_for_in(&mut self, name: Token, iterator: Value, mut resumed: bool, body: Statement, env: &mut Environment) -> Result<Flow, LoxError> {
+        loop {
+            if !resumed {
+                let Some(item) = self.interpret_next(&iterator, env)? else { break };
+                env.push_scope_with(std::slice::from_ref(&name), vec![item]);
+            }
+
+            resumed = false;
+            let flow: Result<Flow, LoxError> = self.interpret_statement(body.clone(), env);
+
+            if let Ok(Flow::Yield(value)) = flow {
+                self.resume_path.push(Resume::ForIn(iterator));
+                return Ok(Flow::Yield(value));
+            }
+
+            env.pop_scope();
+
+            match flow? {
+                Flow::Break => break,
+                Flow::Continue | Flow::Normal => (),
+                flow => return Ok(flow)
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Gets the next item of an iterator, or None once it is done. Iterator objects are done once `next()` returns nil.
+    fn interpret_next(&mut self, iterator: &Value, env: &Environment) -> Result<Option<Value>, LoxError> {
+        match iterator {
+            Value::Iterator(iterator) => return Ok(iterator.borrow_mut().next()),
+            // The value a generator returns is not one of its items
+            Value::Generator(generator) => {
+                let item: Value = self.resume(generator, Value::Nil())?;
+                let done: bool = generator.borrow().is_done();
+
+                return Ok(match done {
+                    true => None,
+                    false => Some(item)
+                });
+            },
+            _ => ()
+        }
+
+        match self.call_callable(iteration_method(iterator, "next")?, Vec::new(), env)? {
+            Value::Nil() => Ok(None),
+            item => Ok(Some(item))
+        }
+    }
+
+    /// Interprets a try statement. The finally block runs however the try block and catch clause finish, and only
+    /// replaces their outcome if it moves control elsewhere itself.
+    fn interpret_try(&mut self, body: Vec<Statement>, catch: Option<(Token, Box<Vec<Statement>>)>, finally: Option<Box<Vec<Statement>>>, env: &mut Environment) -> Result<Flow, LoxError> {
+        // A resumed generator continues in the part of the statement it yielded in
+        let result: Result<Flow, LoxError> = match self.resume_path.pop() {
+            Some(Resume::Catch) => {
+                let (_, handler) = catch.expect("A generator was resumed in a missing catch clause.");
+                self.interpret_catch(*handler, env)
+            },
+            Some(Resume::Finally(result)) => result,
+            _ => match (self.interpret_block(body, env), catch) {
+                (Ok(Flow::Yield(value)), _) => {
+                    self.resume_path.push(Resume::Try);
+                    return Ok(Flow::Yield(value));
+                },
+                (Err(error), Some((name, handler))) => match catch_error(error, env) {
+                    Ok(value) => {
+                        env.push_scope_with(&[name], vec![value]);
+                        self.interpret_catch(*handler, env)
+                    },
+                    Err(error) => Err(error)
                 },
-                _ => Err(LoxError::ValueError(name_value, String::from("'super' does not refer to a class.")))
+                (result, _) => result
             }
+        };
+
+        if let Ok(Flow::Yield(_)) = result {
+            return result;
+        }
+
+        let Some(finally) = finally else { return result };
+
+        match self.interpret_block(*finally, env)? {
+            Flow::Normal => result,
+            Flow::Yield(value) => {
+                self.resume_path.push(Resume::Finally(result));
+                Ok(Flow::Yield(value))
+            },
+            flow => Ok(flow)
         }
     }
-}
 
-/// Interprets an if statement in a new block environment.
-fn interpret_if(cond: Expr, then: Box<Statement>, els: Box<Option<Statement>>, env: &mut Environment) -> Result<Value, LoxError> {
-    let cond_value: Value = interpret_expr(cond, env)?;
+    /// Interprets a catch clause in the scope of its error variable, which is kept while a generator is suspended in
+    /// the clause.
+    fn interpret_catch(&mut self, handler: Vec<Statement>, env: &mut Environment) -> Result<Flow, LoxError> {
+        let result: Result<Flow, LoxError> = self.interpret_statements(handler, env);
 
-    if get_value_truth(cond_value, env)? {
-        interpret_statement(*then, env)
+        if let Ok(Flow::Yield(value)) = result {
+            self.resume_path.push(Resume::Catch);
+            return Ok(Flow::Yield(value));
+        }
+
+        env.pop_scope();
+        result
     }
 
-    else {
-        match *els {
-            None => Ok(Value::Nil()),
-            Some(else_case) => interpret_statement(else_case, env)
+    /// Interprets and calls a callable
+    fn interpret_call(&mut self, name: Box<Expr>, args: Vec<Expr>, env: &mut Environment) -> Result<Value, LoxError> {
+        let called: Value = self.interpret_expr(*name, env)?;
+        let args: Vec<Value> = self.interpret_args(args, env)?;
+        self.call_value(called, args, env)
+    }
+
+    /// Calls a callable or constructs a class with already interpreted arguments.
+    pub fn call_value(&mut self, called: Value, args: Vec<Value>, env: &Environment) -> Result<Value, LoxError> {
+        match called {
+            Value::Callable(callable) => {
+                check_arity(&callable, args.len(), "function")?;
+                self.call_callable(callable, args, env)
+            },
+            Value::Class(class) => self.instantiate(class, args, env),
+            _ => Err(LoxError::ValueError(called, String::from("Value is not callable!")))
         }
     }
-}
 
-/// Interprets a declaration and adds it to the current environment.
-fn interpret_declaration(name: Token, identifier: Option<Expr>, env: &mut Environment) -> Result<(), LoxError> {
-    match identifier {
-        None => Ok(env.define_local(name.lexeme, Value::Nil())),
-        Some(exp) => {
-            match interpret_expr(exp, env) {
-                Ok(value) => Ok(env.define_local(name.lexeme, value)),
-                Err(error) => Err(error)
+    /// Constructs a new instance of a class, calling its constructor if it has one.
+    pub fn instantiate(&mut self, class: Rc<LoxClass>, args: Vec<Value>, env: &Environment) -> Result<Value, LoxError> {
+        let constructor: Option<LoxCallable> = class.find_method(&String::from("init"));
+
+        match constructor {
+            // Defined constructor
+            Some(init) => {
+                check_arity(&init, args.len(), "constructor")?;
+                let obj: Rc<RefCell<LoxObject>> = env.heap().instance(LoxObject::new(class));
+                let init: LoxCallable = init.bind(obj.clone());
+                self.call_callable(init, args, env)?;
+                Ok(Value::Instance(obj))
+            },
+            // Auto constructor
+            None => {
+                check_class_arity(&class, args.len())?;
+                Ok(Value::Instance(env.heap().instance(LoxObject::new(class))))
             }
         }
     }
-}
 
+    /// Calls a callable, keeping track of how deeply calls are nested.
+    fn call_callable(&mut self, callable: LoxCallable, args: Vec<Value>, env: &Environment) -> Result<Value, LoxError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(LoxError::StackOverflow(callable.get_name().lexeme));
+        }
 
-/// Interprets a while loop in a block environment.
-fn interpret_while(condition: Expr, body: Statement, env: &mut Environment) -> Result<Value, LoxError> {
-    while get_value_truth(interpret_expr(condition.clone(), env)?, env)? {
-        let result = interpret_statement(body.clone(), env)?;
+        // The callable's body is not part of the expression a generator is evaluating
+        self.call_depth += 1;
+        let evaluation: Evaluation = std::mem::take(&mut self.evaluation);
+        let result: Result<Value, LoxError> = grow_stack(|| callable.call(args, self, env));
+        self.evaluation = evaluation;
+        self.call_depth -= 1;
 
-        if result != Value::Nil() {
-            return Ok(result)
+        result
+    }
+
+    /// Applies a binary operator, calling the method that overloads it if either operand has one.
+    fn interpret_binary(&mut self, left: Value, op: TokenType, right: Value, env: &Environment) -> Result<Value, LoxError> {
+        match find_binary_method(&left, op, &right) {
+            Some((method, other)) => {
+                let result: Value = self.call_callable(method, vec![other], env)?;
+                operators::overloaded_binary(op, result)
+            },
+            None => operators::binary(left, op, right)
         }
     }
 
-    Ok(Value::Nil())
-}
+    /// Converts a value to a string, calling the `__str__` method of an instance if it has one.
+    fn interpret_str(&mut self, value: &Value, env: &Environment) -> Result<String, LoxError> {
+        match find_method(value, operators::STR_METHOD) {
+            Some(method) => operators::overloaded_str(self.call_callable(method, Vec::new(), env)?),
+            None => Ok(value.to_string())
+        }
+    }
 
-/// Adds a closure to the environment.
-fn interpret_closure(name: Token, args: Vec<Token>, body: Box<Vec<Statement>>, env: &mut Environment) -> Result<(), LoxError> {
-    if env.is_global() {
-        env.define_global(name.lexeme.clone(),
-                          Value::Callable(LoxCallable::Closure(name.lexeme, args, body, Environment::get_block_env(&env), false)))
+    /// Gets a property of a value, calling its getter if it has one.
+    fn interpret_get(&mut self, object: Value, property: &Token, env: &Environment) -> Result<Value, LoxError> {
+        match find_getter(&object, &property.lexeme) {
+            Some(getter) => self.call_callable(getter, Vec::new(), env),
+            None => get_property(object, property)
+        }
     }
 
-    else {
-        Ok(env.define_local(name.lexeme.clone(),
-                            Value::Callable(LoxCallable::Closure(name.lexeme, args, body, Environment::get_block_env(&env), false))))
+    /// Sets a property of a value, calling its setter if it has one.
+    fn interpret_set(&mut self, object: Value, property: Token, value: Value, env: &Environment) -> Result<(), LoxError> {
+        match find_setter(&object, &property.lexeme) {
+            Some(setter) => self.call_callable(setter, vec![value], env).map(|_| ()),
+            None => set_property(object, property, value)
+        }
     }
-}
 
-/// Interprets and calls a callable
-fn interpret_call(name: Box<Expr>, args: Vec<Expr>, env: &mut Environment) -> Result<Value, LoxError> {
-    let called: Value = interpret_expr(*name, env)?;
-    
-    match called {
-        Value::Callable(callable) => {
-            if !callable.check_arity(&args) {
-                return Err(LoxError::ArgumentError(Statement::Expression(Expr::Variable(callable.get_name())), String::from("Invalid arity for function.")));
-            }
+    /// Interprets a vector of function arguments.
+    fn interpret_args(&mut self, args: Vec<Expr>, env: &mut Environment) -> Result<Vec<Value>, LoxError> {
+        let mut interpreted_args: Vec<Value> = Vec::new();
 
-            let args = interpret_args(args, env)?;
+        for arg in args {
+            interpreted_args.push(self.interpret_expr(arg, env)?);
+        }
 
-            callable.call(args, env)
-        },
-        Value::Class(class) => {
-            let constructor: Option<LoxCallable> = class.find_method(&String::from("init"));
+        Ok(interpreted_args)
+    }
 
-            match constructor {
-                // Defined constructor
-                Some(init) => {
-                    if !init.check_arity(&args) {
-                        Err(LoxError::ArgumentError(Statement::Expression(Expr::Variable(init.get_name())), String::from("Invalid arity for constructor.")))
-                    }
+    /// Interprets a class and its associated methods
+    fn interpret_class(&mut self, name: Token, superclass: Option<Expr>, methods: Vec<Statement>, accessors: Vec<Statement>, doc: Option<String>, env: &mut Environment) -> Result<Rc<LoxClass>, LoxError> {
+        let superclass: Option<Rc<LoxClass>> = self.interpret_superclass(superclass, env)?;
+        let mut class_methods: HashMap<String, LoxCallable> = HashMap::new();
+        let mut class_env: Environment = env.clone();
 
-                    else {
-                        let args: Vec<Value> = interpret_args(args, env)?;
-                        let obj: Rc<RefCell<LoxObject>> = Rc::new(RefCell::new(LoxObject::new(class)));
-                        let init: LoxCallable = init.bind(obj.clone());
-                        init.call(args, env)?;
-                        Ok(Value::Instance(obj))
-                    }
+        // Add superclass to env in its own scope
+        if let Some(ref class) = superclass {
+            class_env.push_scope();
+            class_env.define(String::from("super"), Value::Class(class.clone()));
+        }
+
+        // Parse methods
+        for method in methods {
+            let method_declaration: (Token, Vec<Token>, Box<Vec<Statement>>, Option<String>) = method.into_fun_declaration().unwrap();
+            let name = method_declaration.0.lexeme;
+            let method: LoxCallable = LoxCallable::Closure(name.clone(), method_declaration.1, method_declaration.2, class_env.clone(), false, method_declaration.3);
+            class_methods.insert(name, method);
+        }
+
+        // Getters take no parameters, and setters take the assigned value
+        let mut getters: HashMap<String, LoxCallable> = HashMap::new();
+        let mut setters: HashMap<String, LoxCallable> = HashMap::new();
+
+        for accessor in accessors {
+            if let Statement::FunDeclaration(name, params, body, doc) = accessor {
+                let accessors: &mut HashMap<String, LoxCallable> = match params.is_empty() {
+                    true => &mut getters,
+                    false => &mut setters
+                };
+
+                accessors.insert(name.lexeme.clone(), LoxCallable::Closure(name.lexeme, params, body, class_env.clone(), false, doc));
+            }
+        }
+    
+        // Construct and add class
+        let class = LoxClass::new(name.lexeme.clone(), superclass, class_methods, getters, setters, class_env, doc);
+        let class: Rc<LoxClass> = env.heap().class(class);
+        env.define(name.lexeme, Value::Class(class.clone()));
+
+        Ok(class)
+    }
+
+    /// Adds the static members of a class. They are added once the class exists, so they can refer to it.
+    fn interpret_statics(&mut self, class: &Rc<LoxClass>, statics: Vec<Statement>, env: &mut Environment) -> Result<(), LoxError> {
+        for member in statics {
+            match member {
+                Statement::FunDeclaration(name, params, body, doc) => {
+                    let method: LoxCallable = LoxCallable::Closure(name.lexeme.clone(), params, body, env.clone(), false, doc);
+                    class.define_static(name.lexeme, Value::Callable(method));
                 },
-                // Auto constructor
-                None => {
-                    if args.len() > 0 {
-                        Err(LoxError::ArgumentError(Statement::Expression(Expr::Literal(Value::Class(class))), String::from("Invalid arity for constructor.")))
-                    }
+                Statement::Var(name, initializer) => {
+                    let value: Value = match initializer {
+                        Some(initializer) => self.interpret_expr(initializer, env)?,
+                        None => Value::Nil()
+                    };
 
-                    else {
-                        Ok(Value::Instance(Rc::new(RefCell::new(LoxObject::new(class)))))
-                    }
-                }
+                    class.define_static(name.lexeme, value);
+                },
+                _ => ()
             }
-        },
-        _ => Err(LoxError::ValueError(called, String::from("Value is not callable!")))
+        }
+
+        Ok(())
     }
-}
 
-/// Interprets a vector of function arguments.
-fn interpret_args(args: Vec<Expr>, env: &mut Environment) -> Result<Vec<Value>, LoxError> {
-    let mut interpreted_args: Vec<Value> = Vec::new();
+    /// Gets a superclass if it exists and if the superclass given is not None.
+    fn interpret_superclass(&mut self, superclass: Option<Expr>, env: &mut Environment) -> Result<Option<Rc<LoxClass>>, LoxError> {
+        match superclass {
+            Some(class_expr) => {
+                let class_value = self.interpret_expr(class_expr, env)?;
+                let class_result = class_value.into_class();
 
-    for arg in args {
-        interpreted_args.push(interpret_expr(arg, env)?);
+                match class_result {
+                    Ok(class) => Ok(Some(class)),
+                    Err(value) => Err(LoxError::ValueError(value, String::from("A superclass must be a class.")))
+                }
+            },
+            None => Ok(None)
+        }
     }
+}
 
-    Ok(interpreted_args)
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Interprets a class and its associated methods
-fn interpret_class(name: Token, superclass: Option<Expr>, methods: Vec<Statement>, env: &mut Environment) -> Result<(), LoxError> {
-    let superclass: Option<Rc<LoxClass>> = interpret_superclass(superclass, env)?;
-    let mut class_methods: HashMap<String, LoxCallable> = HashMap::new();
-    let mut class_env: Environment = env.clone();
+/// Adds a closure to the environment.
+fn interpret_closure(name: Token, args: Vec<Token>, body: Box<Vec<Statement>>, doc: Option<String>, env: &mut Environment) {
+    let closure: LoxCallable = LoxCallable::Closure(name.lexeme.clone(), args, body, env.clone(), false, doc);
+    env.define(name.lexeme, Value::Callable(closure));
+}
 
-    // Add superclass to env
-    if let Some(ref class) = superclass {
-        class_env.define_local(String::from("super"), Value::Class(class.clone()));
+/// Runs a call, moving it onto a new segment of stack if the current one is close to running out. Lox calls nest Rust
+/// calls, so the call depth limit is reached before the stack runs out, whatever thread the program runs on.
+pub(crate) fn grow_stack<T>(call: impl FnOnce() -> T) -> T {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, call)
+}
+
+/// Checks that a callable is given the amount of arguments it expects.
+pub(crate) fn check_arity(callable: &LoxCallable, arg_count: usize, kind: &str) -> Result<(), LoxError> {
+    if callable.check_arity(arg_count) {
+        Ok(())
     }
 
-    // Parse methods
-    for method in methods {
-        let method_declaration: (Token, Vec<Token>, Box<Vec<Statement>>) = method.into_fun_declaration().unwrap();
-        let name = method_declaration.0.lexeme;
-        let method: LoxCallable = LoxCallable::Closure(name.clone(), method_declaration.1, method_declaration.2, Environment::get_block_env(&class_env), false);
-        class_methods.insert(name, method);
+    else {
+        Err(LoxError::ArgumentError(Statement::Expression(Expr::Variable(callable.get_name(), None)), format!("Invalid arity for {kind}.")))
     }
-    
-    // Construct and add class
-    let class = LoxClass::new(name.lexeme.clone(), superclass, class_methods, class_env);
-    
-    if env.is_global() {
-        env.define_global(name.lexeme, Value::Class(Rc::new(class)))
+}
+
+/// Checks that a class without a constructor is not given any arguments.
+pub(crate) fn check_class_arity(class: &Rc<LoxClass>, arg_count: usize) -> Result<(), LoxError> {
+    if arg_count > 0 {
+        Err(LoxError::ArgumentError(Statement::Expression(Expr::Literal(Value::Class(class.clone()))), String::from("Invalid arity for constructor.")))
     }
 
     else {
-        Ok(env.define_local(name.lexeme, Value::Class(Rc::new(class))))
+        Ok(())
     }
 }
 
-/// Gets a superclass if it exists and if the superclass given is not None.
-fn interpret_superclass(superclass: Option<Expr>, env: &mut Environment) -> Result<Option<Rc<LoxClass>>, LoxError> {
-    match superclass {
-        Some(class_expr) => {
-            let class_value = interpret_expr(class_expr, env)?;
-            let class_result = class_value.into_class();
+/// Converts an error into the value a catch clause binds. Thrown values are caught as they are, and runtime errors
+/// become instances of `Error` with the type of the error, its message and the value it is about. Errors in the
+/// program itself can't be caught.
+pub(crate) fn catch_error(error: LoxError, env: &Environment) -> Result<Value, LoxError> {
+    let (error_type, message, value): (&str, String, Value) = match error {
+        LoxError::Thrown(value) => return Ok(value),
+        LoxError::RuntimeError(_, message) => ("RuntimeError", message, Value::Nil()),
+        LoxError::ValueError(value, message) => ("ValueError", message, value),
+        LoxError::NameError(name, message) => ("NameError", message, Value::Str(name)),
+        LoxError::ArgumentError(_, message) => ("ArgumentError", message, Value::Nil()),
+        LoxError::StackOverflow(name) => ("StackOverflow", String::from("Maximum call depth exceeded."), Value::Str(name)),
+        LoxError::IndexError(index, message) => ("IndexError", message, index),
+        LoxError::ImportError(path, message) => ("ImportError", message, Value::Str(path)),
+        LoxError::ParseError(..) | LoxError::CompilerBug(..) => return Err(error)
+    };
 
-            match class_result {
-                Ok(class) => Ok(Some(class)),
-                Err(value) => Err(LoxError::ValueError(value, String::from("A superclass must be a class.")))
-            }
-        },
-        None => Ok(None)
-    }
+    let class: Rc<LoxClass> = env.heap().class(LoxClass::new(String::from("Error"), None, HashMap::new(), HashMap::new(), HashMap::new(), env.clone(), None));
+    let mut object: LoxObject = LoxObject::new(class);
+    object.fields.insert(String::from("type"), Value::Str(String::from(error_type)));
+    object.fields.insert(String::from("message"), Value::Str(message));
+    object.fields.insert(String::from("value"), value);
+
+    Ok(Value::Instance(env.heap().instance(object)))
 }
 
 /// Gets the truthiness of a value
 fn get_value_truth(value: Value, env: &Environment) -> Result<bool, LoxError> {
+    operators::get_value_truth(resolve_identifier(value, env)?)
+}
+
+/// Replaces an identifier with the value it is bound to.
+fn resolve_identifier(value: Value, env: &Environment) -> Result<Value, LoxError> {
     match value {
-        Value::Bool(truth) => Ok(truth),
-        Value::Identifier(token) => get_value_truth(env.get(&*token)?, env),
-        Value::Nil() => Ok(false),
-        _ => Err(LoxError::ValueError(value, String::from("Value has no truthiness!")))
+        Value::Identifier(token) => env.get(&token),
+        _ => Ok(value)
+    }
+}
+
+/// Gets a property from an instance or host object, a static member of a class, a method of a collection, or a global
+/// of a module.
+pub(crate) fn get_property(object: Value, property: &Token) -> Result<Value, LoxError> {
+    match object {
+        Value::Host(host) => host.get(&property.lexeme),
+        Value::List(list) => LoxList::get_method(&list, &property.lexeme),
+        Value::Map(map) => LoxMap::get_method(&map, &property.lexeme),
+        Value::Iterator(iterator) => LoxIterator::get_method(&iterator, &property.lexeme),
+        Value::Generator(generator) => LoxGenerator::get(&generator, &property.lexeme),
+        Value::Module(module) => module.get(&property.lexeme),
+        Value::Class(class) => class.get_static(&property.lexeme).ok_or_else(|| {
+            LoxError::NameError(property.lexeme.clone(), format!("Undefined property {}", property.lexeme))
+        }),
+        object => get_from_ref(get_object(object)?, property)
+    }
+}
+
+/// Sets a property on an instance or host object, or a static field of a class.
+pub(crate) fn set_property(object: Value, property: Token, value: Value) -> Result<(), LoxError> {
+    match object {
+        Value::Host(host) => host.set(&property.lexeme, value),
+        Value::Module(_) => Err(LoxError::ValueError(object, String::from("Module globals can't be set from outside the module."))),
+        Value::Class(class) => {
+            class.set_static(property.lexeme, value);
+            Ok(())
+        },
+        object => {
+            get_object(object)?.borrow_mut().set(property, value);
+            Ok(())
+        }
     }
 }
 
-/// Builds a new binary expression from given values.
-fn build_binary_expr(left: Value, op: Token, right: Value) -> Expr {
-    Expr::Binary(Box::new(Expr::Literal(left)),
-                 op,
-                 Box::new(Expr::Literal(right)))
+/// Finds a method of an instance's class, bound to the instance. Fields are not searched.
+pub(crate) fn find_method(object: &Value, name: &str) -> Option<LoxCallable> {
+    let Value::Instance(instance) = object else { return None };
+    let method: LoxCallable = instance.borrow().get_class().find_method(&String::from(name))?;
+    Some(method.bind(instance.clone()))
 }
 
-/// Extracts a LoxObject out of an expression.
-fn get_object(obj: Expr, env: &mut Environment) -> Result<Rc<RefCell<LoxObject>>, LoxError> {
-    let object: Value = interpret_expr(obj, env)?;
+/// Finds the method which overloads a binary operator, bound to its operand, with the other operand to call it with.
+/// The left operand's method is used if it has one, and otherwise the reflected method of the right operand.
+pub(crate) fn find_binary_method(left: &Value, op: TokenType, right: &Value) -> Option<(LoxCallable, Value)> {
+    if let Some(method) = operators::binary_method(op).and_then(|name| find_method(left, name)) {
+        return Some((method, right.clone()));
+    }
+
+    let method: LoxCallable = operators::reflected_method(op).and_then(|name| find_method(right, name))?;
+    Some((method, left.clone()))
+}
+
+/// Gets a method of an instance or host object which takes part in iteration, bound to the object.
+pub(crate) fn iteration_method(object: &Value, name: &str) -> Result<LoxCallable, LoxError> {
+    let method: Option<LoxCallable> = match object {
+        Value::Host(host) => host.get(name).ok().and_then(|method| method.into_callable().ok()),
+        _ => find_method(object, name)
+    };
+
+    method.ok_or_else(|| LoxError::ValueError(object.clone(), format!("An iterable object must have a {name}() method.")))
+}
+
+/// Finds the getter of a property of an instance, bound to the instance. Getters take precedence over fields.
+pub(crate) fn find_getter(object: &Value, name: &str) -> Option<LoxCallable> {
+    let Value::Instance(instance) = object else { return None };
+    let getter: LoxCallable = instance.borrow().get_class().find_getter(name)?;
+    Some(getter.bind(instance.clone()))
+}
+
+/// Finds the setter of a property of an instance, bound to the instance. Setters take precedence over fields.
+pub(crate) fn find_setter(object: &Value, name: &str) -> Option<LoxCallable> {
+    let Value::Instance(instance) = object else { return None };
+    let setter: LoxCallable = instance.borrow().get_class().find_setter(name)?;
+    Some(setter.bind(instance.clone()))
+}
+
+/// Gets the item at an index of a list, or the value of a key of a map.
+pub(crate) fn get_index(collection: Value, index: Value) -> Result<Value, LoxError> {
+    match collection {
+        Value::List(list) => list.borrow().get(&index),
+        Value::Map(map) => map.borrow().get(&index),
+        _ => Err(LoxError::ValueError(collection, String::from("Only lists and maps can be indexed.")))
+    }
+}
+
+/// Sets the item at an index of a list, or the value of a key of a map.
+pub(crate) fn set_index(collection: Value, index: Value, value: Value) -> Result<(), LoxError> {
+    match collection {
+        Value::List(list) => list.borrow_mut().set(&index, value),
+        Value::Map(map) => map.borrow_mut().set(index, value),
+        _ => Err(LoxError::ValueError(collection, String::from("Only lists and maps can be indexed.")))
+    }
+}
+
+/// Extracts a LoxObject out of a value.
+fn get_object(object: Value) -> Result<Rc<RefCell<LoxObject>>, LoxError> {
     let object: Result<Rc<RefCell<LoxObject>>, Value> = object.into_instance();
 
     match object {
@@ -504,4 +1139,4 @@ fn get_from_ref(obj_ref: Rc<RefCell<LoxObject>>, property: &Token) -> Result<Val
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,8 @@
 pub mod interpret;
 pub mod environment;
+pub mod context;
+pub mod output;
+pub mod gc;
+pub(crate) mod modules;
+pub mod operators;
 pub(crate) mod stdlib;
\ No newline at end of file
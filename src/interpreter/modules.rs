@@ -0,0 +1,74 @@
+use std::{collections::HashMap, env, ffi::OsStr, fs, iter, path::{Path, PathBuf}, rc::Rc};
+use crate::{LoxError, interpreter::environment::Environment, parse, types::{statement::Statement, values::{Value, module::LoxModule}}};
+
+/// The modules loaded by a program. Each file is loaded once, and later imports of it share the same module.
+#[derive(Default)]
+pub(crate) struct Modules {
+    loaded: HashMap<PathBuf, Rc<LoxModule>>,
+    // The files being loaded, from the program outwards to the module being imported
+    loading: Vec<PathBuf>
+}
+
+impl Modules {
+    /// Sets the file of the program, which is being loaded for as long as it runs.
+    pub(crate) fn set_root(&mut self, path: PathBuf) {
+        self.loading = vec![path];
+    }
+}
+
+/// Imports a module, running it with a backend if it has not been loaded yet.
+///
+/// The path is looked up relative to the file doing the import, then in each directory of `LOX_PATH`.
+pub(crate) fn import(path: &str, env: &Environment, run: impl FnOnce(Vec<Statement>, &mut Environment) -> Result<(), LoxError>) -> Result<Value, LoxError> {
+    let file: PathBuf = find_module(path, env.path())?;
+
+    if let Some(module) = env.modules().borrow().loaded.get(&file) {
+        return Ok(Value::Module(module.clone()));
+    }
+
+    if let Some(start) = env.modules().borrow().loading.iter().position(|loading| *loading == file) {
+        let cycle: Vec<String> = env.modules().borrow().loading[start..].iter()
+                                                                         .chain(iter::once(&file))
+                                                                         .map(|file| file_name(file))
+                                                                         .collect();
+        return Err(LoxError::ImportError(String::from(path), format!("Import cycle: {}.", cycle.join(" -> "))));
+    }
+
+    let source: String = fs::read_to_string(&file).map_err(|err| {
+        LoxError::ImportError(String::from(path), format!("Module could not be read: {err}"))
+    })?;
+    let statements: Vec<Statement> = parse(&source).map_err(|mut errors| errors.remove(0))?;
+    let mut module_env: Environment = env.module(file.clone());
+
+    env.modules().borrow_mut().loading.push(file.clone());
+    let result: Result<(), LoxError> = run(statements, &mut module_env);
+    env.modules().borrow_mut().loading.pop();
+    result?;
+
+    let name: String = file.file_stem().and_then(OsStr::to_str).unwrap_or(path).to_string();
+    let module: Rc<LoxModule> = Rc::new(LoxModule::new(name, module_env));
+    env.modules().borrow_mut().loaded.insert(file, module.clone());
+
+    Ok(Value::Module(module))
+}
+
+/// Finds the file of a module, relative to the directory of the importing file, or the working directory if the
+/// import was not made from a file.
+fn find_module(path: &str, importer: Option<&Path>) -> Result<PathBuf, LoxError> {
+    let directory: PathBuf = importer.and_then(Path::parent).map(Path::to_path_buf).unwrap_or_default();
+    let search_path: Vec<PathBuf> = match env::var_os("LOX_PATH") {
+        Some(search_path) => env::split_paths(&search_path).collect(),
+        None => Vec::new()
+    };
+
+    iter::once(directory).chain(search_path)
+                         .map(|directory| directory.join(path))
+                         .find(|file| file.is_file())
+                         .map(|file| file.canonicalize().unwrap_or(file))
+                         .ok_or_else(|| LoxError::ImportError(String::from(path), String::from("Module could not be found.")))
+}
+
+/// Returns the name of a file, for showing it in errors.
+fn file_name(file: &Path) -> String {
+    file.file_name().map_or_else(|| file.display().to_string(), |name| name.to_string_lossy().to_string())
+}
@@ -0,0 +1,225 @@
+use std::rc::Rc;
+use crate::LoxError;
+use crate::types::{expr::Expr, statement::Statement, token_type::TokenType::{self, *}, values::Value};
+
+/// Generates codes for calculations
+macro_rules! calculate {
+    ($left:ident, $right:ident, $op:tt) => {
+        match $left {
+            Value::Int(l_value) => match $right {
+                Value::Int(r_value) => Ok(Value::Int(l_value $op r_value)),
+                Value::Float(r_value) => Ok(Value::Float(l_value as f64 $op r_value)),
+                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+            },
+            Value::Float(l_value) => match $right {
+                Value::Int(r_value) => Ok(Value::Float(l_value $op r_value as f64 )),
+                Value::Float(r_value) => Ok(Value::Float(l_value $op r_value)),
+                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+            },
+            _ => Err(LoxError::ValueError($left, String::from("Not a number.")))
+        }
+    }
+}
+
+/// Generates code for comparisons
+macro_rules! compare {
+    ($left:ident, $right:ident, $op:tt) => {
+        match $left {
+            Value::Int(l_value) => match $right {
+                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value)),
+                Value::Float(r_value) =>  {
+                    let l_value = l_value as f64;
+                    Ok(Value::Bool(l_value $op r_value))
+                }
+                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+            },
+            Value::Float(l_value) => match $right {
+                Value::Int(r_value) => Ok(Value::Bool(l_value $op r_value as f64)),
+                Value::Float(r_value) => Ok(Value::Bool(l_value $op r_value)),
+                _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+            },
+            Value::None() => {
+                Err(LoxError::ValueError($left, String::from("You cannot compare a value to a keyword.")))
+            },
+            _ => Err(LoxError::ValueError($right, String::from("Not a number.")))
+        }
+    }
+}
+
+/// Generates code for determining equality. Values of different types are never equal, and lists, maps and
+/// instances are only equal to themselves.
+macro_rules! equal {
+    ($left:ident, $right:ident) => {
+        match ($left, $right) {
+            (Value::Str(l_value), Value::Str(r_value)) => Ok(l_value == r_value),
+            (Value::Int(l_value), Value::Int(r_value)) => Ok(l_value == r_value),
+            (Value::Int(l_value), Value::Float(r_value)) => Ok(l_value as f64 == r_value),
+            (Value::Float(l_value), Value::Int(r_value)) => Ok(l_value == r_value as f64),
+            (Value::Float(l_value), Value::Float(r_value)) => Ok(l_value == r_value),
+            (Value::Bool(l_value), Value::Bool(r_value)) => Ok(l_value == r_value),
+            (Value::Nil(), right) => Ok(right == Value::Nil()),
+            (Value::List(l_value), Value::List(r_value)) => Ok(Rc::ptr_eq(&l_value, &r_value)),
+            (Value::Map(l_value), Value::Map(r_value)) => Ok(Rc::ptr_eq(&l_value, &r_value)),
+            (Value::Instance(l_value), Value::Instance(r_value)) => Ok(Rc::ptr_eq(&l_value, &r_value)),
+            (Value::Str(_) | Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::List(_) | Value::Map(_) | Value::Instance(_), _) => Ok(false),
+            (left, _) => {
+                Err(LoxError::ValueError(left, String::from("Value is not comparable.")))
+            }
+        }
+    }
+}
+
+/// Applies a unary operator to a value.
+pub fn unary(op: TokenType, value: Value) -> Result<Value, LoxError> {
+    match op {
+        Minus => {
+            match value {
+                Value::Float(n) => Ok(Value::Float(-n)),
+                Value::Int(n) => Ok(Value::Int(-n)),
+                _ => Err(LoxError::ValueError(value, String::from("Negation operator cannot be used here.")))
+            }
+        }
+
+        Not => {
+            match value {
+                Value::Bool(b) => Ok(Value::Bool(!b)),
+                _ => Err(LoxError::ValueError(value, String::from("Not operator cannot be used here.")))
+            }
+        }
+
+        _ => Err(LoxError::CompilerBug(Statement::Expression(Expr::Literal(value)), format!("Unary operator was created with invalid token type {op}")))
+    }
+}
+
+/// Applies a binary operator to two values.
+pub fn binary(left: Value, op: TokenType, right: Value) -> Result<Value, LoxError> {
+    match op {
+        Minus => calculate!(left, right, -),
+        // Plus does not use the calculate macro, as it can be used for calculation and concatenation
+        Plus => match left {
+            Value::Int(l_value) => match right {
+                Value::Int(r_value) => Ok(Value::Int(l_value + r_value)),
+                Value::Float(r_value) => Ok(Value::Float(l_value as f64 + r_value)),
+                Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                _ => Err(LoxError::ValueError(right, String::from("Not a number.")))
+            },
+            Value::Float(l_value) => match right {
+                Value::Int(r_value) => Ok(Value::Float(l_value + r_value as f64 )),
+                Value::Float(r_value) => Ok(Value::Float(l_value + r_value)),
+                Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                _ => Err(LoxError::ValueError(right, String::from("Not a number.")))
+            },
+            Value::Bool(l_value) => {
+                match right {
+                    Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                    _ => Err(LoxError::ValueError(right, String::from("Not a string.")))
+                }
+            }
+            Value::Str(ref l_value) => {
+                match right {
+                    Value::Int(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                    Value::Float(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                    Value::Str(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                    Value::Bool(r_value) => Ok(Value::Str(format!("{l_value}{r_value}"))),
+                    _ => Err(LoxError::ValueError(right, String::from("Value cannot be concatenated to a string.")))
+                }
+            },
+            _ => Err(LoxError::ValueError(left, String::from("Not a number.")))
+        },
+        // Integers can't be divided by zero, while floats divide to infinity or NaN
+        Slash | Mod if matches!((&left, &right), (Value::Int(_), Value::Int(0))) => {
+            Err(LoxError::ValueError(right, String::from("Division by zero.")))
+        },
+        Slash => calculate!(left, right, /),
+        Asterisk => calculate!(left, right, *),
+        Mod => calculate!(left, right, %),
+        EqualEqual => Ok(Value::Bool(equal!(left, right)?)),
+        Greater => compare!(left, right, >),
+        GreaterEqual => compare!(left, right, >=),
+        Less => compare!(left, right, <),
+        NotEqual => Ok(Value::Bool(!equal!(left, right)?)),
+        LessEqual => compare!(left, right, <=),
+        _ => Err(LoxError::ValueError(left, String::from("Does not have an interpretable value.")))
+    }
+}
+
+/// The method an instance defines to be shown as a string.
+pub const STR_METHOD: &str = "__str__";
+
+/// Returns the method an instance defines to overload a unary operator.
+pub fn unary_method(op: TokenType) -> Option<&'static str> {
+    match op {
+        Minus => Some("__neg__"),
+        _ => None
+    }
+}
+
+/// Returns the method an instance on the left of a binary operator defines to overload it. `!=` is overloaded by
+/// negating `__eq__`.
+pub fn binary_method(op: TokenType) -> Option<&'static str> {
+    match op {
+        Plus => Some("__add__"),
+        Minus => Some("__sub__"),
+        Asterisk => Some("__mul__"),
+        Slash => Some("__div__"),
+        Mod => Some("__mod__"),
+        EqualEqual | NotEqual => Some("__eq__"),
+        Greater => Some("__gt__"),
+        GreaterEqual => Some("__ge__"),
+        Less => Some("__lt__"),
+        LessEqual => Some("__le__"),
+        _ => None
+    }
+}
+
+/// Returns the method an instance on the right of a binary operator defines to overload it, when the left operand
+/// doesn't. Arithmetic uses reflected methods such as `__radd__`, while comparisons use the mirrored comparison, so
+/// `1 < vector` calls `vector.__gt__(1)`.
+pub fn reflected_method(op: TokenType) -> Option<&'static str> {
+    match op {
+        Plus => Some("__radd__"),
+        Minus => Some("__rsub__"),
+        Asterisk => Some("__rmul__"),
+        Slash => Some("__rdiv__"),
+        Mod => Some("__rmod__"),
+        EqualEqual | NotEqual => Some("__eq__"),
+        Greater => Some("__lt__"),
+        GreaterEqual => Some("__le__"),
+        Less => Some("__gt__"),
+        LessEqual => Some("__ge__"),
+        _ => None
+    }
+}
+
+/// Gets the value of a binary operator from the result of the method overloading it.
+pub fn overloaded_binary(op: TokenType, result: Value) -> Result<Value, LoxError> {
+    match op {
+        NotEqual => Ok(Value::Bool(!get_value_truth(result)?)),
+        _ => Ok(result)
+    }
+}
+
+/// Gets the string shown for an instance from the result of its `__str__` method.
+pub fn overloaded_str(result: Value) -> Result<String, LoxError> {
+    match result {
+        Value::Str(string) => Ok(string),
+        _ => Err(LoxError::ValueError(result, format!("{STR_METHOD} must return a string.")))
+    }
+}
+
+/// Applies a logical operator to two values. Only `and` and `or` are logical operators.
+pub fn logical(left: Value, op: TokenType, right: Value) -> Result<bool, LoxError> {
+    match op {
+        And => Ok(get_value_truth(left)? && get_value_truth(right)?),
+        _ => Ok(get_value_truth(left)? || get_value_truth(right)?)
+    }
+}
+
+/// Gets the truthiness of a value
+pub fn get_value_truth(value: Value) -> Result<bool, LoxError> {
+    match value {
+        Value::Bool(truth) => Ok(truth),
+        Value::Nil() => Ok(false),
+        _ => Err(LoxError::ValueError(value, String::from("Value has no truthiness!")))
+    }
+}
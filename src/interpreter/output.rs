@@ -0,0 +1,34 @@
+use std::{cell::RefCell, io::{self, Write}, rc::Rc};
+
+/// An in-memory output sink, for collecting the output of a Lox program as a string.
+///
+/// Clones of a buffer share the same contents, so one clone can be given to the interpreter while another is kept to
+/// read what was written.
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns everything written to the buffer so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    /// Empties the buffer.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
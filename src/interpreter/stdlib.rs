@@ -1,20 +1,61 @@
 use std::{collections::HashMap, time::UNIX_EPOCH};
 use std::time::{Duration, SystemTime};
 use crate::LoxError;
-use crate::types::values::{callable::LoxCallable, Value};
+use crate::interpreter::context::NativeContext;
+use crate::types::values::{callable::{LoxCallable, NativeFn}, iterator::LoxIterator, Value};
 
 pub fn get_stdlib() -> HashMap<String, Value> {
     HashMap::from([
         (
             String::from("clock"),
-            Value::Callable(LoxCallable::Native(String::from("clock"), clock, 0))
+            Value::Callable(LoxCallable::Native(String::from("clock"), NativeFn::new(clock), 0))
+        ),
+        (
+            String::from("gc"),
+            Value::Callable(LoxCallable::Native(String::from("gc"), NativeFn::new(gc), 0))
+        ),
+        (
+            String::from("doc"),
+            Value::Callable(LoxCallable::Native(String::from("doc"), NativeFn::new(doc), 1))
+        ),
+        (
+            String::from("range"),
+            Value::Callable(LoxCallable::Native(String::from("range"), NativeFn::new(range), 2))
         )
     ])
 }
 
 /// Returns the current time in miliseconds.
-fn clock(_: Vec<Value>) -> Result<Value, LoxError> {
+fn clock(_: &mut NativeContext, _: Vec<Value>) -> Result<Value, LoxError> {
     let now: SystemTime = SystemTime::now();
     let duration: Duration = now.duration_since(UNIX_EPOCH).unwrap();
     Ok(Value::Float(duration.as_millis() as f64))
-}
\ No newline at end of file
+}
+
+/// Collects unreachable reference cycles, returning the number of objects freed.
+fn gc(ctx: &mut NativeContext, _: Vec<Value>) -> Result<Value, LoxError> {
+    Ok(Value::Int(ctx.heap().collect() as i64))
+}
+
+/// Returns the doc comment of a function or class, or nil if it was declared without one.
+fn doc(_: &mut NativeContext, mut args: Vec<Value>) -> Result<Value, LoxError> {
+    let doc: Option<String> = match &args[0] {
+        Value::Callable(callable) => callable.doc().map(String::from),
+        Value::Class(class) => class.doc().map(String::from),
+        _ => return Err(LoxError::ValueError(args.remove(0), String::from("Only functions and classes have doc comments.")))
+    };
+
+    Ok(doc.map_or(Value::Nil(), Value::Str))
+}
+
+/// Returns an iterator counting from a start up to, but not including, an end. It counts down if the end is lower.
+fn range(_: &mut NativeContext, mut args: Vec<Value>) -> Result<Value, LoxError> {
+    let end: Value = args.remove(1);
+    let start: Value = args.remove(0);
+
+    match (start, end) {
+        (Value::Int(start), Value::Int(end)) => Ok(LoxIterator::range(start, end)),
+        (Value::Int(_), end) => Err(LoxError::ValueError(end, String::from("A range must be between integers."))),
+        (start, _) => Err(LoxError::ValueError(start, String::from("A range must be between integers.")))
+    }
+}
@@ -0,0 +1,332 @@
+// LoxError carries whole AST nodes and values, and statement bodies are boxed throughout the AST.
+#![allow(clippy::result_large_err, clippy::large_enum_variant, clippy::box_collection)]
+
+use std::io::Write;
+use std::path::PathBuf;
+use crate::interpreter::context::NativeContext;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::gc::GcStats;
+use crate::interpreter::interpret::Interpreter;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanning::Scanner;
+use crate::types::expr::Expr;
+use crate::types::statement::Statement;
+use crate::types::token::Token;
+use crate::types::token_type::TokenType;
+use crate::types::values::Value;
+use crate::vm::compiler::Compiler;
+use crate::vm::machine::Vm;
+use thiserror::Error;
+
+pub mod scanning;
+pub mod parser;
+pub mod resolver;
+pub mod interpreter;
+pub mod types;
+pub mod vm;
+
+/// A handle to a Lox interpreter.
+///
+/// The handle keeps its environment between calls, so globals defined by one call to [`Lox::eval`] are visible
+/// to the next one, and can be read and written from Rust.
+pub struct Lox {
+    interpreter: Interpreter,
+    env: Environment,
+    backend: Backend
+}
+
+/// The ways a [`Lox`] handle can run programs.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Backend {
+    /// Walks the syntax tree of the program.
+    #[default]
+    TreeWalker,
+    /// Compiles the program to bytecode, and runs it on a stack VM.
+    Vm
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self { interpreter: Interpreter::new(), env: Environment::new(), backend: Backend::default() }
+    }
+
+    /// Sets the maximum depth of nested calls. Programs which call deeper fail with [`LoxError::StackOverflow`].
+    ///
+    /// Calls grow the stack as they need to, so any limit can be reached on a thread with the default stack size.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.interpreter.set_max_call_depth(max_call_depth);
+    }
+
+    /// Sets the backend programs are run with.
+    ///
+    /// Globals are kept when switching backends, but Lox functions only run on the backend which defined them. Calling
+    /// a function or method, constructing a class with an initializer, or resuming a generator, which was defined by
+    /// code run on the other backend fails with a [`LoxError::ValueError`]. Other values, such as numbers, strings,
+    /// lists, maps, native functions and host objects, work on either backend.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Sets the sink that print statements write to. Defaults to stdout.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.set_output(output);
+    }
+
+    /// Sets the sink that errors are reported to by [`Lox::run`]. Defaults to stderr.
+    pub fn set_diagnostics(&mut self, diagnostics: impl Write + 'static) {
+        self.interpreter.set_diagnostics(diagnostics);
+    }
+
+    /// Sets the file the programs being run come from. Modules they import are looked up relative to it, rather than
+    /// the working directory.
+    pub fn set_script_path(&mut self, path: impl Into<PathBuf>) {
+        self.env.set_path(path.into());
+    }
+
+    /// Evaluates a Lox program.
+    ///
+    /// If the program ends with an expression statement, the value of that expression is returned. Otherwise,
+    /// nil is returned. Only the first syntax error of a program is returned.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        let mut statements: Vec<Statement> = parse(source).map_err(|mut errs| errs.remove(0))?;
+
+        // Compiled programs already return their trailing expression
+        if self.backend == Backend::Vm {
+            return self.execute(statements);
+        }
+
+        match statements.pop() {
+            Some(Statement::Expression(expr)) => {
+                self.interpreter.interpret(statements, &mut self.env)?;
+                self.interpreter.evaluate(expr, &mut self.env)
+            },
+            Some(stmt) => {
+                statements.push(stmt);
+                self.interpreter.interpret(statements, &mut self.env)?;
+                Ok(Value::Nil())
+            },
+            None => Ok(Value::Nil())
+        }
+    }
+
+    /// Runs a Lox program, reporting any errors to the diagnostics sink.
+    ///
+    /// Returns the error code of the Lox program.
+    pub fn run(&mut self, source: &str) -> i32 {
+        match parse(source) {
+            Ok(stmts) => match self.execute(stmts) {
+                Ok(_) => 0,
+                Err(err) => report_error_type(err, self.interpreter.diagnostics())
+            },
+
+            Err(errs) => {
+                // Report all syntax errors
+                errs.into_iter().for_each(|err| { report_error_type(err, self.interpreter.diagnostics()); });
+
+                // Only a parse error can be thrown at this stage, so we return a parse error value
+                1
+            }
+        }
+    }
+
+    /// Calls a global function or class by name.
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, LoxError> {
+        let callee: Value = self.get_global(name)?;
+
+        match self.backend {
+            Backend::TreeWalker => self.interpreter.call_value(callee, args, &self.env),
+            Backend::Vm => self.vm().call_value(callee, args)
+        }
+    }
+
+    /// Registers a native function as a global.
+    ///
+    /// The function may capture state, such as a database handle or a counter, which it keeps between calls.
+    pub fn define_native(&mut self, name: &str, arity: usize, func: impl Fn(&mut NativeContext, Vec<Value>) -> Result<Value, LoxError> + 'static) {
+        self.env.define_native(name, arity, func);
+    }
+
+    /// Retrieves a global binding.
+    pub fn get_global(&self, name: &str) -> Result<Value, LoxError> {
+        self.env.get_global(name)
+    }
+
+    /// Sets a global binding, defining it if it does not exist yet.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.env.define_global(String::from(name), value);
+    }
+
+    /// Frees reference cycles which are no longer reachable, returning the number of objects freed.
+    ///
+    /// Collections also run automatically as programs allocate objects.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.env.heap().collect()
+    }
+
+    /// Returns statistics about the garbage collections run so far.
+    pub fn gc_stats(&self) -> GcStats {
+        self.env.heap().stats()
+    }
+
+    /// Runs a resolved program with the current backend.
+    fn execute(&mut self, statements: Vec<Statement>) -> Result<Value, LoxError> {
+        match self.backend {
+            Backend::TreeWalker => self.interpreter.interpret(statements, &mut self.env),
+            Backend::Vm => {
+                let function = Compiler::new().compile(&statements)?;
+                self.vm().interpret(function)
+            }
+        }
+    }
+
+    /// Creates a VM which shares the handle's globals and output sink.
+    fn vm(&self) -> Vm {
+        let mut vm = Vm::new(self.env.clone(), self.interpreter.output());
+        vm.set_max_call_depth(self.interpreter.max_call_depth());
+        vm
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scans, parses and resolves a Lox program.
+pub(crate) fn parse(source: &str) -> Result<Vec<Statement>, Vec<LoxError>> {
+    let mut scanner = Scanner::new(source);
+    let tokens: Vec<Token> = scanner.scan_tokens()?;
+    let mut parser = Parser::new(tokens);
+    let parse_results: Vec<Result<Statement, LoxError>> = parser.parse();
+
+    let mut statements: Vec<Statement> = to_statements(parse_results)?;
+    Resolver::new().resolve(&mut statements)?;
+    Ok(statements)
+}
+
+/// Converts a parser result into a vec of statements, if possible
+fn to_statements(results: Vec<Result<Statement, LoxError>>) -> Result<Vec<Statement>, Vec<LoxError>> {
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut errors: Vec<LoxError> = Vec::new();
+    let mut seen_error: bool = false;
+
+    for result in results {
+        match result {
+            Ok(stmt) => {
+                if !seen_error {
+                    statements.push(stmt);
+                }
+            }
+
+            Err(err) => {
+                seen_error = true;
+                errors.push(err);
+            }
+        }
+    };
+
+    if seen_error {
+        Err(errors)
+    }
+
+    else {
+        Ok(statements)
+    }
+}
+
+/// Reports a Lox error.
+fn report(out: &mut dyn Write, identifier: String, at: &str, message: &str) {
+    // There is nowhere left to report a failure to report an error, so it is ignored
+    _ = writeln!(out, "[{identifier}] Error{at}: {message}");
+    _ = out.flush();
+}
+
+/// Reports a Lox error, returning the exit code corresponding to its type.
+pub fn report_error_type(error: LoxError, out: &mut dyn Write) -> i32 {
+    match error {
+        LoxError::ParseError(token, msg) => {
+            if token.token_type == TokenType::End {
+                report(out, token.line.to_string(), " at end", &msg);
+            }
+
+            else {
+                let at: String = format!(" at '{}'", token.lexeme);
+                report(out, token.line.to_string(), &at, &msg);
+            }
+            1
+        },
+        LoxError::RuntimeError(expr, msg) => {
+            let at: String = format!(" in '{expr}'");
+            report(out, String::from("Runtime Error"), &at, &msg);
+            2
+        },
+        LoxError::ValueError(value, msg) => {
+            let at: String = format!(" for '{value}'");
+            report(out, String::from("Value Error"), &at, &msg);
+            3
+        },
+        LoxError::CompilerBug(expr, msg) => {
+            let at: String = format!(" in '{expr}'");
+            report(out, String::from("Compiler Bug"), &at, &msg);
+            4
+        },
+        LoxError::NameError(name, msg) => {
+            let at: String = format!(" for '{name}'");
+            report(out, String::from("Name Error"), &at, &msg);
+            5
+        },
+        LoxError::ArgumentError(args, msg) => {
+            let at: String = format!(" for '{:?}'", args);
+            report(out, String::from("Argument Error"), &at, &msg);
+            6
+        },
+        LoxError::StackOverflow(name) => {
+            let at: String = format!(" in '{name}'");
+            report(out, String::from("Stack Overflow"), &at, "Maximum call depth exceeded.");
+            7
+        },
+        LoxError::IndexError(index, msg) => {
+            let at: String = format!(" for '{index}'");
+            report(out, String::from("Index Error"), &at, &msg);
+            8
+        },
+        LoxError::Thrown(value) => {
+            let at: String = format!(" for '{value}'");
+            report(out, String::from("Uncaught Error"), &at, "Value was thrown and not caught.");
+            9
+        },
+        LoxError::ImportError(path, msg) => {
+            let at: String = format!(" for '{path}'");
+            report(out, String::from("Import Error"), &at, &msg);
+            10
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum LoxError {
+    #[error("Syntax error: ")]
+    ParseError(Token, String),
+    #[error("Runtime error: ")]
+    RuntimeError(Expr, String),
+    #[error("Value error: ")]
+    ValueError(Value, String),
+    #[error("Compiler bug: ")]
+    CompilerBug(Statement, String),
+    #[error("Name error: ")]
+    NameError(String, String),
+    #[error("Argument error: ")]
+    ArgumentError(Statement, String),
+    #[error("Stack overflow: ")]
+    StackOverflow(String),
+    #[error("Index error: ")]
+    IndexError(Value, String),
+    /// A value thrown by a throw statement, which was not caught.
+    #[error("Uncaught error: ")]
+    Thrown(Value),
+    /// A module which could not be imported, by the path it was imported with.
+    #[error("Import error: ")]
+    ImportError(String, String)
+}
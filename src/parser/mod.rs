@@ -1,16 +1,40 @@
+use std::collections::HashMap;
 use crate::LoxError;
 use crate::types::{expr::Expr, token::Token, values::Value, token_type::TokenType::{self, *}, statement::Statement};
 
-/// TODO: Allow underscores to be used in names.
-
 pub struct Parser {
     tokens: Vec<Token>,
-    current: usize
+    current: usize,
+    // The number of loops enclosing the current statement, within the current function
+    loop_depth: usize,
+    // Whether the current function has a yield expression, which makes it a generator
+    yields: bool,
+    // Doc comments, by the index of the token after them
+    docs: HashMap<usize, String>
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut doc: Vec<String> = Vec::new();
+        let mut kept: Vec<Token> = Vec::new();
+
+        // Consecutive doc comment lines are joined and attached to the token after them
+        for token in tokens {
+            if token.token_type == Doc {
+                doc.push(token.literal.to_string());
+                continue;
+            }
+
+            if !doc.is_empty() {
+                docs.insert(kept.len(), doc.join("\n"));
+                doc.clear();
+            }
+
+            kept.push(token);
+        }
+
+        Self { tokens: kept, current: 0, loop_depth: 0, yields: false, docs }
     }
 
     /// Builds all ASTs needed for a program
@@ -18,10 +42,12 @@ impl Parser {
         let mut statements: Vec<Result<Statement, LoxError>> = Vec::new();
 
         while !self.is_at_end() {
+            // A statement which failed to parse may have left loops open
+            self.loop_depth = 0;
             let statement_result = self.statement();
 
             // We love band-aid fixes, don't we folks
-            if let Err(_) = statement_result {
+            if statement_result.is_err() {
                 self.advance();
             }
 
@@ -33,8 +59,17 @@ impl Parser {
 
     /// Builds ASTs for statements
     fn statement(&mut self) -> Result<Statement, LoxError> {
-        if self.match_token(&[Class]) { self.class_declaration() }
-        else if self.match_token(&[Fun]) { self.function_declaration("function") }
+        if self.check(&Class) {
+            let doc: Option<String> = self.doc_comment();
+            self.advance();
+            self.class_declaration(doc)
+        }
+        // A function without a name is a lambda, which starts an expression statement
+        else if self.check(&Fun) && !self.check_next(&LeftParen) {
+            let doc: Option<String> = self.doc_comment();
+            self.advance();
+            self.function_declaration("function", doc)
+        }
         else if self.match_token(&[Var]) {
             match self.declaration() {
                 Ok(stmt) => Ok(stmt),
@@ -46,8 +81,13 @@ impl Parser {
         }
         else if self.match_token(&[For]) { self.for_statement() }
         else if self.match_token(&[If]) { self.if_statement() }
+        else if self.match_token(&[Import]) { self.import_statement() }
+        else if self.match_token(&[From]) { self.import_names_statement() }
         else if self.match_token(&[Print]) { self.print_statement() }
         else if self.match_token(&[Return]) { self.return_statement() }
+        else if self.match_token(&[Break, Continue]) { self.loop_control_statement() }
+        else if self.match_token(&[Throw]) { self.throw_statement() }
+        else if self.match_token(&[Try]) { self.try_statement() }
         else if self.match_token(&[While]) { self.while_statement() }
         else if self.match_token(&[LeftBrace]) { Ok(Statement::Block(self.block()?)) }
         else { self.expression_statement() }
@@ -112,26 +152,26 @@ impl Parser {
         self.consume(LeftParen, "Expect '(' after 'while'.")?;
         let condition: Expr = self.expression()?;
         self.consume(RightParen, "Expect '(' after 'while'.")?;
-        let body: Statement = self.statement()?;
+        let body: Statement = self.loop_body()?;
 
-        Ok(Statement::While(condition, Box::new(body)))
+        Ok(Statement::While(condition, Box::new(body), None))
     }
 
-    /// Consumes expressions.
+    /// Consumes expressions, which can also be increments.
     fn expression_statement(&mut self) -> Result<Statement, LoxError> {
-        let expr: Result<Expr, LoxError> = self.expression();
+        let expr: Expr = self.increment()?;
         self.consume(Semicolon, "Expect ';' after value.")?;
-        
-        match expr {
-            Ok(exp) => Ok(Statement::Expression(exp)),
-            Err(err) => Err(err)
-        }
+        Ok(Statement::Expression(expr))
     }
 
     /// Consumes for statements.
     fn for_statement(&mut self) -> Result<Statement, LoxError> {
         self.consume(LeftParen, "Expect '(' after 'for'.")?;
 
+        if self.check(&Identifier) && self.check_next(&In) {
+            return self.for_in_statement();
+        }
+
         // Build initializer
         let mut initializer: Option<Statement> = None;
 
@@ -157,19 +197,14 @@ impl Parser {
         let mut increment: Option<Expr> = None;
 
         if !self.check(&RightParen) {
-            increment = Some(self.expression()?);
+            increment = Some(self.increment()?);
         }
         
         self.consume(RightParen, "Expect ')' after for clauses.")?;
 
         // Build body
-        let mut body: Statement = self.statement()?;
-
-        if let Some(expr) = increment {
-            body = Statement::Block(Box::new(vec![body, Statement::Expression(expr)]));
-        };
-
-        body = Statement::While(condition, Box::new(body));
+        let body: Statement = self.loop_body()?;
+        let mut body: Statement = Statement::While(condition, Box::new(body), increment);
 
         if let Some(expr) = initializer {
             body = Statement::Block(Box::new(vec![expr, body]));
@@ -178,10 +213,116 @@ impl Parser {
         Ok(body)
     }
 
-    /// Consumes functions.
-    fn function_declaration(&mut self, kind: &str) -> Result<Statement, LoxError> {
+    /// Builds the AST for a loop over the items of a value, after the opening parenthesis.
+    fn for_in_statement(&mut self) -> Result<Statement, LoxError> {
+        let name: Token = self.advance();
+        self.advance();
+
+        let iterable: Expr = self.expression()?;
+        self.consume(RightParen, "Expect ')' after for clauses.")?;
+        let body: Statement = self.loop_body()?;
+
+        Ok(Statement::ForIn(name, iterable, Box::new(body)))
+    }
+
+    /// Consumes the body of a loop.
+    fn loop_body(&mut self) -> Result<Statement, LoxError> {
+        self.loop_depth += 1;
+        let body: Result<Statement, LoxError> = self.statement();
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    /// Consumes break and continue statements.
+    fn loop_control_statement(&mut self) -> Result<Statement, LoxError> {
+        let keyword: Token = self.previous();
+        self.consume(Semicolon, format!("Expect ';' after '{}'.", keyword.lexeme).as_str())?;
+
+        if self.loop_depth == 0 {
+            return Err(LoxError::ParseError(keyword.clone(), format!("Can't use '{}' outside of a loop.", keyword.lexeme)));
+        }
+
+        match keyword.token_type {
+            Break => Ok(Statement::Break(keyword)),
+            _ => Ok(Statement::Continue(keyword))
+        }
+    }
+
+    /// Consumes throw statements.
+    fn throw_statement(&mut self) -> Result<Statement, LoxError> {
+        let keyword: Token = self.previous();
+        let value: Expr = self.expression()?;
+        self.consume(Semicolon, "Expect ';' after thrown value.")?;
+
+        Ok(Statement::Throw(keyword, value))
+    }
+
+    /// Consumes imports of whole modules, which bind the module to a name.
+    fn import_statement(&mut self) -> Result<Statement, LoxError> {
+        let path: Token = self.consume(Str, "Expect module path after 'import'.")?;
+        self.consume(As, "Expect 'as' after module path.")?;
+        let name: Token = self.consume(Identifier, "Expect module name after 'as'.")?;
+        self.consume(Semicolon, "Expect ';' after import.")?;
+
+        Ok(Statement::Import(path, name))
+    }
+
+    /// Consumes imports of names from a module.
+    fn import_names_statement(&mut self) -> Result<Statement, LoxError> {
+        let path: Token = self.consume(Str, "Expect module path after 'from'.")?;
+        self.consume(Import, "Expect 'import' after module path.")?;
+
+        let mut names: Vec<Token> = vec![self.consume(Identifier, "Expect name to import.")?];
+        while self.match_token(&[Comma]) {
+            names.push(self.consume(Identifier, "Expect name to import.")?);
+        }
+
+        self.consume(Semicolon, "Expect ';' after import.")?;
+
+        Ok(Statement::FromImport(path, names))
+    }
+
+    /// Consumes try statements, which need a catch clause, a finally block or both.
+    fn try_statement(&mut self) -> Result<Statement, LoxError> {
+        let keyword: Token = self.previous();
+        self.consume(LeftBrace, "Expect '{' after 'try'.")?;
+        let body: Box<Vec<Statement>> = self.block()?;
+
+        let mut catch: Option<(Token, Box<Vec<Statement>>)> = None;
+        if self.match_token(&[Catch]) {
+            self.consume(LeftParen, "Expect '(' after 'catch'.")?;
+            let name: Token = self.consume(Identifier, "Expect error variable name.")?;
+            self.consume(RightParen, "Expect ')' after error variable name.")?;
+            self.consume(LeftBrace, "Expect '{' before catch body.")?;
+            catch = Some((name, self.block()?));
+        }
+
+        let mut finally: Option<Box<Vec<Statement>>> = None;
+        if self.match_token(&[Finally]) {
+            self.consume(LeftBrace, "Expect '{' after 'finally'.")?;
+            finally = Some(self.block()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(LoxError::ParseError(keyword, String::from("Expect 'catch' or 'finally' after try block.")));
+        }
+
+        Ok(Statement::Try(body, catch, finally))
+    }
+
+    /// Consumes functions, along with their doc comment.
+    fn function_declaration(&mut self, kind: &str, doc: Option<String>) -> Result<Statement, LoxError> {
         let name: Token = self.consume(Identifier, format!("Expect {kind} name.").as_str())?;
         self.consume(LeftParen, format!("Expect '(' after {kind} name.").as_str())?;
+        let params: Vec<Token> = self.parameters()?;
+        let body: Box<Vec<Statement>> = self.function_body(kind)?;
+
+        Ok(Statement::FunDeclaration(name, params, body, doc))
+    }
+
+    /// Consumes the parameters of a function, after its opening parenthesis.
+    fn parameters(&mut self) -> Result<Vec<Token>, LoxError> {
         let mut params: Vec<Token> = Vec::new();
 
         // Consume params.
@@ -205,15 +346,78 @@ impl Parser {
         }
         self.consume(RightParen, "Expect ')' after parameters.")?;
 
-        // Consume body.
+        Ok(params)
+    }
+
+    /// Consumes the body of a function. Loops around the function do not continue into it.
+    fn function_body(&mut self, kind: &str) -> Result<Box<Vec<Statement>>, LoxError> {
         self.consume(LeftBrace, format!("Expect '{{' before {kind} body.").as_str())?;
-        let body: Box<Vec<Statement>> = self.block()?;
+        let enclosing_loops: usize = self.loop_depth;
+        let enclosing_yields: bool = self.yields;
+        self.loop_depth = 0;
+        self.yields = false;
+        let body: Result<Box<Vec<Statement>>, LoxError> = self.block();
+        self.loop_depth = enclosing_loops;
+        let yields: bool = std::mem::replace(&mut self.yields, enclosing_yields);
+
+        match yields {
+            true => Ok(Box::new(vec![Statement::Generator(body?)])),
+            false => body
+        }
+    }
+
+    /// Consumes anonymous functions, after the 'fun' keyword.
+    fn lambda(&mut self) -> Result<Expr, LoxError> {
+        let name: Token = Token::new(Identifier, String::from("lambda"), Value::Nil(), self.previous().line);
+        self.consume(LeftParen, "Expect '(' after 'fun'.")?;
+        let params: Vec<Token> = self.parameters()?;
+        let body: Box<Vec<Statement>> = self.function_body("function")?;
+
+        Ok(Expr::Lambda(name, params, body))
+    }
+
+    /// Consumes arrow functions, after their opening parenthesis. The body is an expression, which is returned.
+    fn arrow_function(&mut self) -> Result<Expr, LoxError> {
+        let name: Token = Token::new(Identifier, String::from("lambda"), Value::Nil(), self.previous().line);
+        let params: Vec<Token> = self.parameters()?;
+        let arrow: Token = self.consume(Arrow, "Expect '=>' after parameters.")?;
+        let enclosing_yields: bool = std::mem::replace(&mut self.yields, false);
+        let value: Result<Expr, LoxError> = self.expression();
+        let yields: bool = std::mem::replace(&mut self.yields, enclosing_yields);
+        let body: Vec<Statement> = vec![Statement::Return(arrow, value?)];
 
-        Ok(Statement::FunDeclaration(name, params, body))
+        match yields {
+            true => Ok(Expr::Lambda(name, params, Box::new(vec![Statement::Generator(Box::new(body))]))),
+            false => Ok(Expr::Lambda(name, params, Box::new(body)))
+        }
+    }
+
+    /// Checks if the parenthesis at the current token starts the parameters of an arrow function.
+    fn is_arrow_function(&self) -> bool {
+        let mut current: usize = self.current + 1;
+
+        if self.tokens[current].token_type != RightParen {
+            loop {
+                if self.tokens[current].token_type != Identifier {
+                    return false;
+                }
+
+                current += 1;
+
+                match self.tokens[current].token_type {
+                    Comma => current += 1,
+                    RightParen => break,
+                    _ => return false
+                }
+            }
+        }
+
+        self.tokens[current + 1].token_type == Arrow
     }
 
     /// Consume return statements.
     fn return_statement(&mut self) -> Result<Statement, LoxError> {
+        let keyword: Token = self.previous();
         let mut value: Expr = Expr::Literal(Value::Nil());
 
         // Interpret return value, if it exists.
@@ -222,11 +426,24 @@ impl Parser {
         }
 
         self.consume(Semicolon, "Expect ';' after return value.")?;
-        Ok(Statement::Return(value))
+        Ok(Statement::Return(keyword, value))
     }
 
-    /// Consumes class declarations.
-    fn class_declaration(&mut self) -> Result<Statement, LoxError> {
+    /// Consumes yield expressions, after the 'yield' keyword. A bare yield yields nil.
+    fn yield_expression(&mut self) -> Result<Expr, LoxError> {
+        let keyword: Token = self.previous();
+        let mut value: Expr = Expr::Literal(Value::Nil());
+
+        if ![Semicolon, RightParen, RightBracket, RightBrace, Comma].iter().any(|end| self.check(end)) {
+            value = self.expression()?;
+        }
+
+        self.yields = true;
+        Ok(Expr::Yield(keyword, Box::new(value)))
+    }
+
+    /// Consumes class declarations, along with their doc comment.
+    fn class_declaration(&mut self, doc: Option<String>) -> Result<Statement, LoxError> {
         // Get names
         let name: Token = self.consume(Identifier, "Expect class name.")?;
 
@@ -234,20 +451,63 @@ impl Parser {
         let mut superclass: Option<Expr> = None;
         if self.match_token(&[Less]) {
             self.consume(Identifier, "Expect superclass name.")?;
-            superclass = Some(Expr::Variable(self.previous()));
+            superclass = Some(Expr::Variable(self.previous(), None));
         }
 
         // Get body
         self.consume(LeftBrace, "Expect '{' before class body.")?;
         let mut methods: Vec<Statement> = Vec::new();
+        let mut accessors: Vec<Statement> = Vec::new();
+        let mut statics: Vec<Statement> = Vec::new();
 
         while !self.check(&RightBrace) && !self.is_at_end() {
-            methods.push(self.function_declaration("method")?);
+            let doc: Option<String> = self.doc_comment();
+
+            // Static members start with `static` or `class`, and are methods or fields
+            if self.match_token(&[Static, Class]) {
+                if self.check_next(&LeftParen) {
+                    statics.push(self.function_declaration("static method", doc)?);
+                }
+
+                else {
+                    statics.push(self.declaration()?);
+                }
+            }
+
+            // Getters have no parameter list, and setters are declared like `name = (value) {}`
+            else if self.check(&Identifier) && self.check_next(&LeftBrace) {
+                let name: Token = self.advance();
+                accessors.push(Statement::FunDeclaration(name, Vec::new(), self.function_body("getter")?, doc));
+            }
+
+            else if self.check(&Identifier) && self.check_next(&Equal) {
+                accessors.push(self.setter_declaration(doc)?);
+            }
+
+            else {
+                methods.push(self.function_declaration("method", doc)?);
+            }
         }
 
         self.consume(RightBrace, "Expect '}' after class body.")?;
 
-        Ok(Statement::Class(name, superclass, Box::new(methods)))
+        Ok(Statement::Class(name, superclass, Box::new(methods), Box::new(accessors), Box::new(statics), doc))
+    }
+
+    /// Consumes setters, which take the assigned value as their only parameter.
+    fn setter_declaration(&mut self, doc: Option<String>) -> Result<Statement, LoxError> {
+        let name: Token = self.advance();
+        self.advance();
+        self.consume(LeftParen, "Expect '(' after '=' in setter.")?;
+        let params: Vec<Token> = self.parameters()?;
+        let body: Box<Vec<Statement>> = self.function_body("setter")?;
+
+        // Checked after the body, so that parsing carries on from the end of the setter
+        if params.len() != 1 {
+            return Err(LoxError::ParseError(name, String::from("A setter must have exactly one parameter.")));
+        }
+
+        Ok(Statement::FunDeclaration(name, params, body, doc))
     }
 
     /// Builds ASTs for expressions
@@ -266,17 +526,71 @@ impl Parser {
             let value: Expr = self.assignment()?;
 
             match exp {
-                Expr::Variable(name) => Ok(Expr::Assign(name, Box::from(value))),
+                Expr::Variable(name, _) => Ok(Expr::Assign(name, Box::from(value), None)),
                 Expr::Get(object, property) => Ok(Expr::Set(object, property, Box::from(value))),
+                Expr::Index(list, index) => Ok(Expr::SetIndex(list, index, Box::from(value))),
                 _ => Err(LoxError::ParseError(equals, String::from("Invalid assignment target.")))
             }
         }
 
+        else if self.match_token(&[PlusEqual, MinusEqual, AsteriskEqual, SlashEqual, ModEqual]) {
+            let op: Token = self.previous();
+            let value: Expr = self.assignment()?;
+            self.update(exp, op, value)
+        }
+
         else {
             Ok(exp)
         }
     }
 
+    /// Builds the AST of an assignment which applies an operator to its target and a value. The parts of the target
+    /// are only evaluated once.
+    fn update(&self, target: Expr, op: Token, value: Expr) -> Result<Expr, LoxError> {
+        let binary_op: TokenType = match op.token_type {
+            PlusEqual | PlusPlus => Plus,
+            MinusEqual | MinusMinus => Minus,
+            AsteriskEqual => Asterisk,
+            SlashEqual => Slash,
+            _ => Mod
+        };
+
+        let lexeme: String = op.lexeme[..1].to_string();
+        let binary_op: Token = Token::new(binary_op, lexeme, Value::None(), op.line);
+
+        match target {
+            Expr::Variable(name, _) => {
+                let current: Expr = Expr::Variable(name.clone(), None);
+                Ok(Expr::Assign(name, Box::new(Expr::Binary(Box::new(current), binary_op, Box::new(value))), None))
+            },
+            Expr::Get(object, property) => Ok(Expr::UpdateProperty(object, property, binary_op, Box::new(value))),
+            Expr::Index(collection, index) => Ok(Expr::UpdateIndex(collection, index, binary_op, Box::new(value))),
+            _ => Err(LoxError::ParseError(op, String::from("Invalid assignment target.")))
+        }
+    }
+
+    /// Builds the AST of an expression, or of an increment or decrement of a variable, property or index. Increments
+    /// work like `+= 1` and don't have a value, so they are only parsed as a whole statement or for increment.
+    fn increment(&mut self) -> Result<Expr, LoxError> {
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            let op: Token = self.previous();
+            let target: Expr = self.call()?;
+            return self.update(target, op, Expr::Literal(Value::Int(1)));
+        }
+
+        let expr: Expr = self.expression()?;
+
+        if !self.match_token(&[PlusPlus, MinusMinus]) {
+            return Ok(expr);
+        }
+
+        // Only a call, property or index can be followed by a postfix increment
+        match expr {
+            Expr::Variable(..) | Expr::Get(..) | Expr::Index(..) => self.update(expr, self.previous(), Expr::Literal(Value::Int(1))),
+            _ => Err(increment_error(&self.previous()))
+        }
+    }
+
     /// Builds ASTs for logical ors
     fn or(&mut self) -> Result<Expr, LoxError> {
         let mut exp: Expr = self.and()?;
@@ -346,7 +660,7 @@ impl Parser {
     fn factor(&mut self) -> Result<Expr, LoxError> {
         let mut exp: Expr = self.unary()?;
 
-        while self.match_token(&[Slash, Asterisk]) {
+        while self.match_token(&[Slash, Asterisk, Mod]) {
             let op: Token = self.previous();
             let right: Expr = self.unary()?;
             exp = Expr::Binary(Box::new(exp), op, Box::new(right));
@@ -363,10 +677,15 @@ impl Parser {
             return Ok(Expr::Unary(op, Box::new(right)));
         }
 
+        // Increments are parsed as statements, so one here is inside another expression
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            return Err(increment_error(&self.previous()));
+        }
+
         self.call()
     }
 
-    /// Builds ASTs for function calls
+    /// Builds ASTs for function calls and property and index accesses
     fn call(&mut self) -> Result<Expr, LoxError> {
         let mut expr: Expr = self.primary()?;
 
@@ -380,6 +699,12 @@ impl Parser {
                 expr = Expr::Get(Box::new(expr), name)
             }
 
+            else if self.match_token(&[LeftBracket]) {
+                let index: Expr = self.expression()?;
+                self.consume(RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index))
+            }
+
             else {
                 break;
             }
@@ -411,6 +736,26 @@ impl Parser {
         Ok(Expr::Call(Box::new(callee), Box::new(arguments)))
     }
 
+    /// Consumes an interpolated string, which is desugared into concatenating its parts and the display form of each
+    /// interpolated expression.
+    fn interpolation(&mut self) -> Result<Expr, LoxError> {
+        let plus: Token = Token::new(Plus, String::from("+"), Value::None(), self.previous().line);
+        let mut string: Expr = Expr::Literal(self.previous().literal);
+
+        loop {
+            let value: Expr = Expr::Stringify(Box::new(self.expression()?));
+            string = Expr::Binary(Box::new(string), plus.clone(), Box::new(value));
+
+            // The string continues up to the next interpolated expression, or to its end
+            if !self.match_token(&[Interpolation]) {
+                let end: Token = self.consume(Str, "Expect '}' after interpolated expression.")?;
+                return Ok(Expr::Binary(Box::new(string), plus, Box::new(Expr::Literal(end.literal))));
+            }
+
+            string = Expr::Binary(Box::new(string), plus.clone(), Box::new(Expr::Literal(self.previous().literal)));
+        }
+    }
+
     /// Parses literals and basic expressions.
     fn primary(&mut self) -> Result<Expr, LoxError> {
         match self.tokens[self.current].token_type {
@@ -430,31 +775,93 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Literal(self.previous().literal))
             },
+            Interpolation => {
+                self.advance();
+                self.interpolation()
+            },
             Super => {
                 self.advance();
                 let keyword: Token = self.previous();
                 self.consume(Dot, "Expect '.' after 'super'.")?;
                 let method: Token = self.consume(Identifier, "Expect superclass method name.")?;
-                Ok(Expr::Super(keyword, method))
+                Ok(Expr::Super(keyword, method, None))
             }
             This => {
                 self.advance();
-                Ok(Expr::This(self.previous()))
+                Ok(Expr::This(self.previous(), None))
             }
+            Yield => {
+                self.advance();
+                self.yield_expression()
+            },
             Identifier => {
                 self.advance();
-                Ok(Expr::Variable(self.previous()))
+                Ok(Expr::Variable(self.previous(), None))
             }
+            LeftParen if self.is_arrow_function() => {
+                self.advance();
+                self.arrow_function()
+            },
+            Fun => {
+                self.advance();
+                self.lambda()
+            },
             LeftParen => {
                 self.advance();
                 let exp: Expr = self.equality()?;
                 self.consume(RightParen, "Expect ')' after expression.")?;
                 Ok(exp)
             },
+            LeftBracket => {
+                self.advance();
+                self.list()
+            },
+            LeftBrace => {
+                self.advance();
+                self.map()
+            },
             _ => Err(LoxError::ParseError(self.tokens[self.current].clone(), String::from("Invalid literal.")))
         }
     }
 
+    /// Parses the elements of a list literal.
+    fn list(&mut self) -> Result<Expr, LoxError> {
+        let mut elements: Vec<Expr> = Vec::new();
+
+        if !self.check(&RightBracket) {
+            loop {
+                elements.push(self.expression()?);
+
+                if !self.match_token(&[Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expr::List(Box::new(elements)))
+    }
+
+    /// Parses the entries of a map literal.
+    fn map(&mut self) -> Result<Expr, LoxError> {
+        let mut entries: Vec<(Expr, Expr)> = Vec::new();
+
+        if !self.check(&RightBrace) {
+            loop {
+                let key: Expr = self.expression()?;
+                self.consume(Colon, "Expect ':' after map key.")?;
+                entries.push((key, self.expression()?));
+
+                if !self.match_token(&[Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::Map(Box::new(entries)))
+    }
+
     /// Checks if token is in list of tokens
     fn match_token(&mut self, token_types: &[TokenType]) -> bool {
         for token_type in token_types {
@@ -473,7 +880,20 @@ impl Parser {
             return false;
         }
 
-        return self.peek().token_type == *token_type;
+        self.peek().token_type == *token_type
+    }
+
+    /// Checks if the token after the current token has token type
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == *token_type,
+            None => false
+        }
+    }
+
+    /// Returns the doc comment written before the current token, if it has one
+    fn doc_comment(&self) -> Option<String> {
+        self.docs.get(&self.current).cloned()
     }
 
     /// Advances the parser forward by one token
@@ -482,12 +902,12 @@ impl Parser {
             self.current += 1;
         }
 
-        return self.previous();
+        self.previous()
     }
 
     /// Checks if parser is finished
     fn is_at_end(&self) -> bool {
-        return self.peek().token_type == End;
+        self.peek().token_type == End
     }
 
     /// Returns next parser token
@@ -506,6 +926,11 @@ impl Parser {
             return Ok(self.advance());
         }
 
+        // An increment can only end a statement, so one found anywhere else is inside another expression
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            return Err(increment_error(&self.previous()));
+        }
+
         Err(LoxError::ParseError(self.peek().clone(), msg.to_string()))
     }
     
@@ -518,12 +943,17 @@ impl Parser {
                 return;
             }
 
-            let _ = match self.peek().token_type {
-                Class | Fun | Var | For | If | While | Print | Return => return,
+            match self.peek().token_type {
+                Class | Fun | Var | For | If | While | Print | Return | Break | Continue | Throw | Try | Import | From | Static => return,
                 _ => ()
             };
 
             self.advance();
         }
     }
-}
\ No newline at end of file
+}
+
+/// Returns the error for an increment or decrement inside another expression.
+fn increment_error(op: &Token) -> LoxError {
+    LoxError::ParseError(op.clone(), String::from("Increments can only be used as statements."))
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use crate::LoxError;
+use crate::types::{expr::{Depth, Expr}, statement::Statement, token::Token, values::Value};
+
+/// Statically resolves every variable in a program to the scope it was declared in.
+///
+/// The resolver runs between the parser and the interpreter. It fills in the depth of every variable, `this` and
+/// `super` expression, and reports errors which can be found without running the program.
+pub struct Resolver {
+    // Each scope maps a name to whether its initializer has been resolved yet
+    scopes: Vec<HashMap<String, bool>>,
+    function: FunctionType,
+    class: ClassType,
+    errors: Vec<LoxError>
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+    // The initializer of a static field, which runs as the class is declared
+    Static
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+    // The static members of a class, which have no instance
+    Static
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            function: FunctionType::None,
+            class: ClassType::None,
+            errors: Vec::new()
+        }
+    }
+
+    /// Resolves a program, returning every static error found in it.
+    pub fn resolve(&mut self, statements: &mut [Statement]) -> Result<(), Vec<LoxError>> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        }
+
+        else {
+            Err(self.errors.drain(..).collect())
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &mut [Statement]) {
+        for stmt in statements {
+            self.resolve_statement(stmt);
+        }
+    }
+
+    /// Resolves each type of statement
+    fn resolve_statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Block(statements) => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            },
+            Statement::Class(name, superclass, methods, accessors, statics, _) => {
+                self.resolve_class(name, superclass, methods, accessors, statics)
+            },
+            Statement::FunDeclaration(name, params, body, _) => {
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            },
+            Statement::Expression(expr) | Statement::Print(expr) => self.resolve_expr(expr),
+            Statement::FromImport(_, names) => names.iter().for_each(|name| {
+                self.declare(name);
+                self.define(name);
+            }),
+            Statement::If(cond, then, els) => {
+                self.resolve_expr(cond);
+                self.resolve_statement(then);
+
+                if let Some(els) = &mut **els {
+                    self.resolve_statement(els);
+                }
+            },
+            Statement::Import(_, name) => {
+                self.declare(name);
+                self.define(name);
+            },
+            Statement::Return(keyword, expr) => {
+                if self.function == FunctionType::None {
+                    self.error(keyword.clone(), "Can't return from top-level code.");
+                }
+
+                // A bare return is parsed as returning nil
+                else if self.function == FunctionType::Initializer && *expr != Expr::Literal(Value::Nil()) {
+                    self.error(keyword.clone(), "Can't return a value from an initializer.");
+                }
+
+                self.resolve_expr(expr);
+            },
+            // The body runs in the scope of the function's parameters
+            Statement::Generator(body) => self.resolve_statements(body),
+            Statement::Var(name, initializer) => {
+                self.declare(name);
+
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+
+                self.define(name);
+            },
+            Statement::While(cond, body, increment) => {
+                self.resolve_expr(cond);
+                self.resolve_statement(body);
+
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+            },
+            Statement::ForIn(name, iterable, body) => {
+                self.resolve_expr(iterable);
+
+                // The variable is bound in a scope of its own for each iteration
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.resolve_statement(body);
+                self.end_scope();
+            },
+            Statement::Break(_) | Statement::Continue(_) => (),
+            Statement::Throw(_, expr) => self.resolve_expr(expr),
+            Statement::Try(body, catch, finally) => {
+                self.begin_scope();
+                self.resolve_statements(body);
+                self.end_scope();
+
+                // The error is bound in the scope of the catch body
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_statements(handler);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.resolve_statements(finally);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    /// Resolves a class and its methods, which are enclosed in a scope declaring `this`, and a scope declaring `super`
+    /// if the class has a superclass.
+    fn resolve_class(&mut self, name: &Token, superclass: &mut Option<Expr>, methods: &mut [Statement], accessors: &mut [Statement], statics: &mut [Statement]) {
+        let enclosing_class: ClassType = self.class;
+        self.class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable(superclass_name, _) = superclass && superclass_name.lexeme == name.lexeme {
+                self.error(superclass_name.clone(), "A class can't inherit from itself.");
+            }
+
+            self.class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+            self.begin_scope();
+            self.declare_str("super");
+        }
+
+        self.begin_scope();
+        self.declare_str("this");
+
+        for method in methods {
+            if let Statement::FunDeclaration(method_name, params, body, _) = method {
+                let function: FunctionType = match method_name.lexeme.as_str() {
+                    "init" => FunctionType::Initializer,
+                    _ => FunctionType::Method
+                };
+
+                self.resolve_function(params, body, function);
+            }
+        }
+
+        for accessor in accessors {
+            if let Statement::FunDeclaration(_, params, body, _) = accessor {
+                self.resolve_function(params, body, FunctionType::Method);
+            }
+        }
+
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        // Static members are set on the class after it is declared, outside of its scopes
+        self.class = ClassType::Static;
+
+        for member in statics {
+            match member {
+                Statement::FunDeclaration(_, params, body, _) => self.resolve_function(params, body, FunctionType::Function),
+                Statement::Var(_, Some(initializer)) => {
+                    let enclosing_function: FunctionType = std::mem::replace(&mut self.function, FunctionType::Static);
+                    self.resolve_expr(initializer);
+                    self.function = enclosing_function;
+                },
+                _ => ()
+            }
+        }
+
+        self.class = enclosing_class;
+    }
+
+    /// Resolves a function body in a new scope containing its parameters.
+    fn resolve_function(&mut self, params: &[Token], body: &mut [Statement], function: FunctionType) {
+        let enclosing_function: FunctionType = self.function;
+        self.function = function;
+
+        self.begin_scope();
+
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.function = enclosing_function;
+    }
+
+    /// Resolves each type of expression
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Literal(_) => (),
+            Expr::Unary(_, right) => self.resolve_expr(right),
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            },
+            Expr::Grouping(expr) => self.resolve_expr(expr),
+            Expr::Call(callee, args) => {
+                self.resolve_expr(callee);
+
+                for arg in args.iter_mut() {
+                    self.resolve_expr(arg);
+                }
+            },
+            Expr::Variable(name, depth) => {
+                if let Some(scope) = self.scopes.last() && scope.get(&name.lexeme) == Some(&false) {
+                    self.error(name.clone(), "Can't read local variable in its own initializer.");
+                }
+
+                *depth = self.resolve_local(name);
+            },
+            Expr::Assign(name, value, depth) => {
+                self.resolve_expr(value);
+                *depth = self.resolve_local(name);
+            },
+            Expr::Get(object, _) => self.resolve_expr(object),
+            Expr::List(elements) => {
+                for element in elements.iter_mut() {
+                    self.resolve_expr(element);
+                }
+            },
+            Expr::Lambda(_, params, body) => self.resolve_function(params, body, FunctionType::Function),
+            Expr::Map(entries) => {
+                for (key, value) in entries.iter_mut() {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            },
+            Expr::Stringify(expr) => self.resolve_expr(expr),
+            Expr::Index(list, index) => {
+                self.resolve_expr(list);
+                self.resolve_expr(index);
+            },
+            Expr::SetIndex(list, index, value) => {
+                self.resolve_expr(list);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            },
+            Expr::UpdateProperty(object, _, _, value) => {
+                self.resolve_expr(object);
+                self.resolve_expr(value);
+            },
+            Expr::UpdateIndex(collection, index, _, value) => {
+                self.resolve_expr(collection);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            },
+            Expr::Set(object, _, value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            },
+            Expr::Super(keyword, _, depth) => {
+                match self.class {
+                    ClassType::None => self.error(keyword.clone(), "Can't use 'super' outside of a class."),
+                    ClassType::Static => self.error(keyword.clone(), "Can't use 'super' in a static member."),
+                    ClassType::Class => self.error(keyword.clone(), "Can't use 'super' in a class with no superclass."),
+                    ClassType::Subclass => ()
+                }
+
+                *depth = self.resolve_local(keyword);
+            },
+            Expr::This(keyword, depth) => {
+                match self.class {
+                    ClassType::None => self.error(keyword.clone(), "Can't use 'this' outside of a class."),
+                    ClassType::Static => self.error(keyword.clone(), "Can't use 'this' in a static member."),
+                    ClassType::Class | ClassType::Subclass => ()
+                }
+
+                *depth = self.resolve_local(keyword);
+            },
+            Expr::Yield(keyword, value) => {
+                if self.function == FunctionType::None {
+                    self.error(keyword.clone(), "Can't yield outside of a function.");
+                }
+
+                else if self.function == FunctionType::Initializer {
+                    self.error(keyword.clone(), "Can't yield from an initializer.");
+                }
+
+                else if self.function == FunctionType::Static {
+                    self.error(keyword.clone(), "Can't yield from a static field initializer.");
+                }
+
+                self.resolve_expr(value);
+            }
+        }
+    }
+
+    /// Finds the depth of a local variable. Variables which are not found are assumed to be globals.
+    fn resolve_local(&self, name: &Token) -> Depth {
+        self.scopes.iter()
+                   .rev()
+                   .position(|scope| scope.contains_key(&name.lexeme))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Declares a variable in the innermost scope, without marking it as ready for use.
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.contains_key(&name.lexeme) {
+                self.error(name.clone(), "Already a variable with this name in this scope.");
+                return;
+            }
+
+            scope.insert(name.lexeme.clone(), false);
+        }
+    }
+
+    /// Declares and defines an implicit variable, such as `this`.
+    fn declare_str(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(String::from(name), true);
+        }
+    }
+
+    /// Marks a variable as ready for use.
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn error(&mut self, token: Token, message: &str) {
+        self.errors.push(LoxError::ParseError(token, String::from(message)));
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
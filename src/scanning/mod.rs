@@ -1,40 +1,54 @@
 use std::num::{ParseFloatError, ParseIntError};
 use crate::types::{token::Token, values::Value, token_type::TokenType::{self, *}};
-use crate::throw;
+use crate::LoxError;
 use ternop::ternary;
 
 pub struct Scanner {
     source: Vec<char>,
     tokens: Vec<Token>,
-    loc: Location
+    errors: Vec<LoxError>,
+    loc: Location,
+    /// The number of unclosed braces inside each interpolated expression being scanned, from the outermost string to the
+    /// innermost.
+    interpolations: Vec<usize>
 }
 
 impl Scanner {
     /// Returns a new scanner
-    pub fn new(source: &String) -> Scanner {
+    pub fn new(source: &str) -> Scanner {
         Scanner { 
             source: source.chars().collect(), 
             tokens: Vec::new(),
-            loc: Location::new()
+            errors: Vec::new(),
+            loc: Location::new(),
+            interpolations: Vec::new()
         }
     }
 
-    /// Scans all tokens given to the scanner
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
+    /// Scans all tokens given to the scanner, returning every error found if the source could not be scanned
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<LoxError>> {
         // Scan while there are more tokens
         while !self.is_at_end() {
             self.loc.start = self.loc.current;
             self.scan_token();
         }
 
+        if !self.interpolations.is_empty() {
+            self.error(End, "Unterminated string interpolation.");
+        }
+
         // Append end token
         self.tokens.push(Token::new(TokenType::End, 
                                     String::new(), 
                                     Value::None(), 
                                     self.loc.line));
 
+        if !self.errors.is_empty() {
+            return Err(self.errors.drain(..).collect());
+        }
+
         // Drain and return tokens
-        self.tokens.drain(..).collect()
+        Ok(self.tokens.drain(..).collect())
     }
 
     /// Scans and parses a single token
@@ -45,15 +59,31 @@ impl Scanner {
             // Single-char tokens
             '(' => self.add_token(LeftParen, None),
             ')' => self.add_token(RightParen, None),
-            '{' => self.add_token(LeftBrace, None),
-            '}' => self.add_token(RightBrace, None),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+
+                self.add_token(LeftBrace, None)
+            },
+            // A brace which closes an interpolated expression continues its string
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.consume_string();
+                },
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(RightBrace, None)
+                },
+                None => self.add_token(RightBrace, None)
+            },
+            '[' => self.add_token(LeftBracket, None),
+            ']' => self.add_token(RightBracket, None),
+            ':' => self.add_token(Colon, None),
             ',' => self.add_token(Comma, None),
             '.' => self.add_token(Dot, None),
-            '-' => self.add_token(Minus, None),
-            '+' => self.add_token(Plus, None),
             ';' => self.add_token(Semicolon, None),
-            '*' => self.add_token(Asterisk, None),
-            '%' => self.add_token(Mod, None),
 
             // One or more char tokens
             '!' => {
@@ -61,8 +91,51 @@ impl Scanner {
                 self.add_token(ternary!(next_is_equal, NotEqual, Not), None)
             },
             '=' => {
+                if self.match_char('=') {
+                    self.add_token(EqualEqual, None);
+                }
+
+                else if self.match_char('>') {
+                    self.add_token(Arrow, None);
+                }
+
+                else {
+                    self.add_token(Equal, None);
+                }
+            },
+            '+' => {
+                if self.match_char('+') {
+                    self.add_token(PlusPlus, None);
+                }
+
+                else if self.match_char('=') {
+                    self.add_token(PlusEqual, None);
+                }
+
+                else {
+                    self.add_token(Plus, None);
+                }
+            },
+            '-' => {
+                if self.match_char('-') {
+                    self.add_token(MinusMinus, None);
+                }
+
+                else if self.match_char('=') {
+                    self.add_token(MinusEqual, None);
+                }
+
+                else {
+                    self.add_token(Minus, None);
+                }
+            },
+            '*' => {
                 let next_is_equal = self.match_char('=');
-                self.add_token(ternary!(next_is_equal, EqualEqual, Equal), None)
+                self.add_token(ternary!(next_is_equal, AsteriskEqual, Asterisk), None)
+            },
+            '%' => {
+                let next_is_equal = self.match_char('=');
+                self.add_token(ternary!(next_is_equal, ModEqual, Mod), None)
             },
             '<' => {
                 let next_is_equal = self.match_char('=');
@@ -73,13 +146,27 @@ impl Scanner {
                 self.add_token(ternary!(next_is_equal, GreaterEqual, Greater), None)
             },
             '/' => {
+                // Doc comment case. Four or more slashes make a normal comment
+                if self.peek() == '/' && self.peek_at(1) == '/' && self.peek_at(2) != '/' {
+                    self.consume_doc_comment();
+                }
+
                 // Comment case
-                if self.match_char('/') {
+                else if self.match_char('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
                 }
 
+                // Block comment case
+                else if self.match_char('*') {
+                    self.consume_block_comment();
+                }
+
+                else if self.match_char('=') {
+                    self.add_token(SlashEqual, None);
+                }
+
                 // Division op case
                 else {
                     self.add_token(Slash, None);
@@ -99,12 +186,12 @@ impl Scanner {
                 }
 
                 // Identifiers
-                else if c.is_alphabetic() {
+                else if c.is_alphabetic() || c == '_' {
                     self.consume_identifier();
                 }
 
                 else {
-                    throw(self.loc.line.to_string(), "Unexpected character.");
+                    self.error(Identifier, "Unexpected character.");
                 }
             }
         }
@@ -130,6 +217,13 @@ impl Scanner {
         self.tokens.push(Token::new(token_type, text, literal, self.loc.line));
     }
 
+    /// Records an error at the current token
+    fn error(&mut self, token_type: TokenType, message: &str) {
+        let text: String = ternary!(token_type == End, String::new(), self.get_token_string(self.loc.start, self.loc.current));
+        let token: Token = Token::new(token_type, text, Value::None(), self.loc.line);
+        self.errors.push(LoxError::ParseError(token, String::from(message)));
+    }
+
     /// Matches current char to expected char
     fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
@@ -146,38 +240,139 @@ impl Scanner {
 
     /// Peeks at current character
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0'
+        self.peek_at(0)
+    }
+
+    /// Peeks at a character a number of characters after the current one
+    fn peek_at(&self, offset: usize) -> char {
+        match self.source.get(self.loc.current + offset) {
+            Some(c) => *c,
+            None => '\0'
         }
+    }
+
+    /// Consumes a doc comment after its first slash, keeping its text without the slashes.
+    fn consume_doc_comment(&mut self) {
+        self.advance();
+        self.advance();
+        self.match_char(' ');
 
-        return self.source[self.loc.current];
+        let text_start: usize = self.loc.current;
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+
+        let text: String = self.get_token_string(text_start, self.loc.current);
+        self.add_token(Doc, Some(Value::Str(String::from(text.trim_end()))));
     }
 
-    /// Parses and consumes a string from the array
+    /// Consumes a block comment after its opening `/*`. Block comments can be nested.
+    fn consume_block_comment(&mut self) {
+        let mut depth: usize = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error(End, "Unterminated block comment.");
+                return;
+            }
+
+            match self.advance() {
+                '\n' => self.loc.line += 1,
+                '/' if self.match_char('*') => depth += 1,
+                '*' if self.match_char('/') => depth -= 1,
+                _ => ()
+            }
+        }
+    }
+
+    /// Parses and consumes a string from the array, stopping early at the start of an interpolated expression.
     fn consume_string(&mut self) {
+        let mut value: String = String::new();
+
         // Capture string contents
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.loc.line += 1;
-            }
+            match self.advance() {
+                '\\' => {
+                    if let Some(c) = self.consume_escape() {
+                        value.push(c);
+                    }
+                },
+                '$' if self.peek() == '{' => {
+                    self.advance();
+                    self.add_token(Interpolation, Some(Value::Str(value)));
+                    self.interpolations.push(0);
+                    return;
+                },
+                c => {
+                    if c == '\n' {
+                        self.loc.line += 1;
+                    }
 
-            self.advance();
+                    value.push(c);
+                }
+            }
         }
 
         // Unterminated string case
         if self.is_at_end() {
-            throw(self.loc.line.to_string(), "Unterminated string.");
+            self.error(End, "Unterminated string.");
             return;
         }
 
         // Consume closing quote
         self.advance();
-
-        // Trim surrounding quotes
-        let value: String = self.get_token_string(self.loc.start + 1, self.loc.current - 1);
         self.add_token(Str, Some(Value::Str(value)));
     }
 
+    /// Consumes the rest of an escape sequence after its backslash, returning the character it stands for.
+    fn consume_escape(&mut self) -> Option<char> {
+        if self.is_at_end() {
+            return None;
+        }
+
+        match self.advance() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            '$' => Some('$'),
+            'u' => self.consume_unicode_escape(),
+            _ => {
+                self.error(Str, "Invalid escape sequence.");
+                None
+            }
+        }
+    }
+
+    /// Consumes a unicode escape of up to six hex digits in braces, e.g. `\u{1F600}`.
+    fn consume_unicode_escape(&mut self) -> Option<char> {
+        if !self.match_char('{') {
+            self.error(Str, "Expect '{' after '\\u'.");
+            return None;
+        }
+
+        let digits_start: usize = self.loc.current;
+        while self.peek().is_ascii_hexdigit() && self.loc.current - digits_start < 6 {
+            self.advance();
+        }
+
+        let digits: String = self.get_token_string(digits_start, self.loc.current);
+
+        if !self.match_char('}') {
+            self.error(Str, "Expect '}' after unicode escape.");
+            return None;
+        }
+
+        let c: Option<char> = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32);
+        if c.is_none() {
+            self.error(Str, "Invalid unicode escape.");
+        }
+
+        c
+    }
+
     /// Parses and consumes a number from the array
     fn consume_number(&mut self) {
         while self.peek().is_digit(DIGIT_RADIX) {
@@ -196,7 +391,7 @@ impl Scanner {
             // Cast to float
             let float: Result<f64, ParseFloatError> = self.get_token_string(self.loc.start, self.loc.current).parse::<f64>();
             let float: f64 = float.unwrap_or_else(|_| {
-                throw(self.loc.line.to_string(), "Float larger than 1.7976931348623157E+308");
+                self.error(Float, "Float larger than 1.7976931348623157E+308");
                 0.0
             });
 
@@ -206,16 +401,16 @@ impl Scanner {
         // Int case
         let int: Result<i64, ParseIntError> = self.get_token_string(self.loc.start, self.loc.current).parse::<i64>();
         let int: i64 = int.unwrap_or_else(|_| {
-            throw(self.loc.line.to_string(), "Integer larger than 9,223,372,036,854,775,807");
+            self.error(Int, "Integer larger than 9,223,372,036,854,775,807");
             0
         });
 
-        return self.add_token(Int, Some(Value::Int(int)));
+        self.add_token(Int, Some(Value::Int(int)))
     }
 
     /// Parses and consumes an identifier from the array
     fn consume_identifier(&mut self) {
-        while self.peek().is_alphanumeric() {
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
@@ -232,21 +427,33 @@ impl Scanner {
 
         match keyword {
             "and" => And,
+            "as" => As,
+            "break" => Break,
+            "catch" => Catch,
             "class" => Class,
+            "continue" => Continue,
             "else" => Else,
             "false" => False,
+            "finally" => Finally,
             "for" => For,
+            "from" => From,
             "fun" => Fun,
             "if" => If,
+            "import" => Import,
+            "in" => In,
             "nil" => Nil,
             "or" => Or,
             "print" => Print,
             "return" => Return,
+            "static" => Static,
             "super" => Super,
             "this" => This,
+            "throw" => Throw,
             "true" => True,
+            "try" => Try,
             "var" => Var,
             "while" => While,
+            "yield" => Yield,
             _ => Identifier
         }
     }
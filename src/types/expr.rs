@@ -1,5 +1,5 @@
 use strum_macros::Display;
-use crate::types::{token::Token, values::Value};
+use crate::types::{statement::Statement, token::Token, values::Value};
 
 #[derive(Clone, Debug, Display, PartialEq)]
 pub enum Expr {
@@ -16,15 +16,37 @@ pub enum Expr {
     // e.g. function()
     Call(Box<Expr>, Box<Vec<Expr>>),
     // e.g. a
-    Variable(Token),
+    Variable(Token, Depth),
     // e.g. a = 5
-    Assign(Token, Box<Expr>),
+    Assign(Token, Box<Expr>, Depth),
     // e.g. object.property
     Get(Box<Expr>, Token),
     // e.g. object.property = value
     Set(Box<Expr>, Token, Box<Expr>),
+    // e.g. [1, 2, 3]
+    List(Box<Vec<Expr>>),
+    // e.g. {"key": value}
+    Map(Box<Vec<(Expr, Expr)>>),
+    // e.g. list[0] or map["key"]
+    Index(Box<Expr>, Box<Expr>),
+    // e.g. list[0] = value or map["key"] = value
+    SetIndex(Box<Expr>, Box<Expr>, Box<Expr>),
+    // e.g. object.property += value, with the operator applied to the property and the value
+    UpdateProperty(Box<Expr>, Token, Token, Box<Expr>),
+    // e.g. list[0] += value or map["key"]++
+    UpdateIndex(Box<Expr>, Box<Expr>, Token, Box<Expr>),
+    // e.g. fun (a) { return a; } or (a) => a
+    Lambda(Token, Vec<Token>, Box<Vec<Statement>>),
+    // e.g. the value in "${value}", which is converted to its display form
+    Stringify(Box<Expr>),
     // e.g. super.method()
-    Super(Token, Token),
+    Super(Token, Token, Depth),
     // e.g. this.property
-    This(Token)
-}
\ No newline at end of file
+    This(Token, Depth),
+    // e.g. yield value, which evaluates to the value the generator is resumed with
+    Yield(Token, Box<Expr>)
+}
+
+/// The number of scopes between a variable and its declaration, which is filled in by the resolver. Globals have no
+/// depth.
+pub type Depth = Option<usize>;
\ No newline at end of file
@@ -5,14 +5,30 @@ use crate::types::{expr::Expr, token::Token};
 #[derive(Clone, Debug, PartialEq, EnumAsInner)]
 pub enum Statement {
     Block(Box<Vec<Statement>>),
-    Class(Token, Option<Expr>, Box<Vec<Statement>>),
-    FunDeclaration(Token, Vec<Token>, Box<Vec<Statement>>),
+    Break(Token),
+    // Classes and functions keep the doc comment written before them. Classes have their instance methods, their
+    // getters and setters, then their static methods and fields
+    Class(Token, Option<Expr>, Box<Vec<Statement>>, Box<Vec<Statement>>, Box<Vec<Statement>>, Option<String>),
+    Continue(Token),
+    FunDeclaration(Token, Vec<Token>, Box<Vec<Statement>>, Option<String>),
     Expression(Expr),
+    // A loop over the items of a value, by the variable each item is bound to
+    ForIn(Token, Expr, Box<Statement>),
+    // An import of the names of a module, by the module's path
+    FromImport(Token, Vec<Token>),
+    // The body of a function which yields. Calling the function returns a generator which runs the body
+    Generator(Box<Vec<Statement>>),
     If(Expr, Box<Statement>, Box<Option<Statement>>),
+    // An import of a whole module, by its path and the name it is bound to
+    Import(Token, Token),
     Print(Expr),
-    Return(Expr),
+    Return(Token, Expr),
+    Throw(Token, Expr),
+    // A try block, its catch clause with the variable the error is bound to, and its finally block
+    Try(Box<Vec<Statement>>, Option<(Token, Box<Vec<Statement>>)>, Option<Box<Vec<Statement>>>),
     Var(Token, Option<Expr>),
-    While(Expr, Box<Statement>),
+    // The increment of a desugared for loop runs after the body, even if the body continues
+    While(Expr, Box<Statement>, Option<Expr>),
 }
 
 impl Display for Statement {
@@ -25,17 +41,25 @@ impl Display for Statement {
 
                 Ok(())
             },
-            Self::Class(name,_, _) => write!(f, "{name}"),
-            Self::FunDeclaration(name, _, _) => write!(f, "{}", name.lexeme),
+            Self::Break(_) => write!(f, "break"),
+            Self::Class(name, ..) => write!(f, "{name}"),
+            Self::Continue(_) => write!(f, "continue"),
+            Self::FunDeclaration(name, _, _, _) => write!(f, "{}", name.lexeme),
             Self::Expression(exp) => write!(f, "{exp}"),
+            Self::ForIn(name, iterable, body) => write!(f, "for {} in {iterable} | {body}", name.lexeme),
+            Self::FromImport(path, _) => write!(f, "from {path} import"),
+            Self::Generator(_) => write!(f, "generator"),
             Self::If(cond, then, els) => match &**els {
                 None => write!(f, "{cond} | {} ", *then),
                 Some(els) => write!(f, "{cond} | {} | {}", *then, *els)
             }
+            Self::Import(path, name) => write!(f, "import {path} as {}", name.lexeme),
             Self::Print(exp) => write!(f, "print {exp}"),
-            Self::Return(exp) => write!(f, "return {exp}"),
+            Self::Return(_, exp) => write!(f, "return {exp}"),
+            Self::Throw(_, exp) => write!(f, "throw {exp}"),
+            Self::Try(..) => write!(f, "try"),
             Self::Var(name, _) => write!(f, "{}", name.lexeme),
-            Self::While(cond, body) => write!(f, "while {cond} | {body}")
+            Self::While(cond, body, _) => write!(f, "while {cond} | {body}")
         }
     }
 }
\ No newline at end of file
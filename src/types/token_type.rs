@@ -3,21 +3,26 @@ use strum_macros::Display;
 #[derive(Copy, Clone, Display, PartialEq, Debug)]
 pub enum TokenType {
     // Single-character tokens
-    LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Asterisk, Mod,
+    LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
+    Colon, Comma, Dot, Minus, Plus, Semicolon, Slash, Asterisk, Mod,
 
     // One or two character tokens
     Not, NotEqual,
-    Equal, EqualEqual,
+    Equal, EqualEqual, Arrow,
+    PlusEqual, PlusPlus, MinusEqual, MinusMinus,
+    AsteriskEqual, SlashEqual, ModEqual,
     Greater, GreaterEqual,
     Less, LessEqual,
 
-    // Literals
-    Identifier, Str, Int, Float,
+    // Literals. An interpolation is the part of a string before an interpolated expression
+    Identifier, Str, Interpolation, Int, Float,
 
     // Keywords
-    And, Class, Else, False, Fun, For, If, Nil, Or,
-    Print, Return, Super, This, True, Var, While,
+    And, As, Break, Catch, Class, Continue, Else, False, Finally, From, Fun, For, If, Import, In, Nil,
+    Or, Print, Return, Static, Super, This, Throw, True, Try, Var, While, Yield,
+
+    // A `///` comment, which documents the declaration after it
+    Doc,
 
     End
 }
\ No newline at end of file
@@ -1,25 +1,28 @@
-use std::{cell::RefCell, fmt::{self, Display}, rc::Rc};
+use std::{cell::RefCell, fmt::{self, Debug, Display}, rc::Rc};
 use enum_as_inner::EnumAsInner;
-use crate::{LoxError, interpreter::{environment::Environment, interpret::interpret}, types::{expr::Expr, statement::Statement, token::Token, values::{Value, object::LoxObject}}};
+use crate::{LoxError, interpreter::{context::NativeContext, environment::Environment, interpret::Interpreter}, types::{statement::Statement, token::Token, values::{Value, object::LoxObject}}};
 use crate::types::token_type::TokenType;
+use crate::interpreter::gc::{Trace, address};
+use crate::vm::function::Closure;
 
 // Callables have to derive PartialEq because they are inside of the Value enum. In practice, they should not be compared to each other anywhere in the code.
 #[derive(Clone, Debug, PartialEq, EnumAsInner)]
 pub enum LoxCallable {
-    Native(String, fn(Vec<Value>) -> Result<Value, LoxError>, usize),
-    Closure(String, Vec<Token>, Box<Vec<Statement>>, Environment, bool)
+    Native(String, NativeFn, usize),
+    // A function run by the tree-walking interpreter, whether it is an initializer, and its doc comment
+    Closure(String, Vec<Token>, Box<Vec<Statement>>, Environment, bool, Option<String>),
+    // A function compiled for the bytecode VM, and the instance it is bound to if it is a method
+    Compiled(Rc<Closure>, Option<Rc<RefCell<LoxObject>>>)
 }
 
 impl LoxCallable {
     /// Borrows and calls the callable's inner function.
-    pub fn call(self, arg_values: Vec<Value>, globals_env: &Environment) -> Result<Value, LoxError> {
+    pub fn call(self, arg_values: Vec<Value>, interpreter: &mut Interpreter, globals_env: &Environment) -> Result<Value, LoxError> {
         match self {
-            LoxCallable::Native(_, func, _) => func(arg_values),
-            LoxCallable::Closure(_, arg_names, body, env, is_init) => {
-                let args_env = Environment::build(&arg_names, &arg_values);
-                let mut env = env + args_env;
-                env.add_globals(globals_env);
-                let result: Value = interpret(*body, &mut env)?;
+            LoxCallable::Native(_, func, _) => func.call(&mut NativeContext::new(interpreter, globals_env), arg_values),
+            LoxCallable::Closure(_, arg_names, body, mut env, is_init, _) => {
+                env.push_scope_with(&arg_names, arg_values);
+                let result: Value = interpreter.interpret(*body, &mut env)?;
 
                 if is_init {
                     // Returns the constructed result if method is init
@@ -31,38 +34,51 @@ impl LoxCallable {
                 else {
                     Ok(result)
                 }
-            }
+            },
+            LoxCallable::Compiled(..) => Err(LoxError::ValueError(Value::Callable(self), String::from("Function can only be called by the bytecode VM.")))
         }
     }
 
-    pub fn check_arity(&self, other: &Vec<Expr>) -> bool {
+    pub fn check_arity(&self, arg_count: usize) -> bool {
         match self {
-            Self::Native(_, _, arity) => *arity == other.len(),
-            Self::Closure(_, tokens, _, _, _) => tokens.len() == other.len()
+            Self::Native(_, _, arity) => *arity == arg_count,
+            Self::Closure(_, tokens, _, _, _, _) => tokens.len() == arg_count,
+            Self::Compiled(closure, _) => closure.function.arity == arg_count
         }
     }
     
     pub fn get_name(&self) -> Token {
         match self {
             Self::Native(name, _, _) => Token::new(TokenType::Identifier, name.clone(), Value::Nil(), 0),
-            Self::Closure(name, _, _, _, _) => Token::new(TokenType::Identifier, name.clone(), Value::Nil(), 0)
+            Self::Closure(name, _, _, _, _, _) => Token::new(TokenType::Identifier, name.clone(), Value::Nil(), 0),
+            Self::Compiled(closure, _) => Token::new(TokenType::Identifier, closure.function.name.clone(), Value::Nil(), 0)
+        }
+    }
+
+    /// Returns the doc comment written before the function declaration. Native functions don't have one.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Self::Native(..) => None,
+            Self::Closure(_, _, _, _, _, doc) => doc.as_deref(),
+            Self::Compiled(closure, _) => closure.function.doc.as_deref()
         }
     }
 
     /// Binds a method to an object, returning the new binded version of that object.
     pub fn bind(self, instance: Rc<RefCell<LoxObject>>) -> Self {
-        let closure: (String, Vec<Token>, Box<Vec<Statement>>, Environment, bool) = self.into_closure().expect("Bind was used on a native function.");
-        let mut object_env = Environment::from(closure.3);
-        
-        object_env.define_local(String::from("this"), Value::Instance(instance));
-
-        if closure.0 == String::from("init") {
-            LoxCallable::Closure(closure.0, closure.1, closure.2, object_env, true)
+        if let Self::Compiled(closure, _) = self {
+            return Self::Compiled(closure, Some(instance));
         }
 
-        else {
-            LoxCallable::Closure(closure.0, closure.1, closure.2, object_env, closure.4)
-        }
+        let Self::Closure(name, params, body, mut object_env, is_init, doc) = self else {
+            panic!("Bind was used on a native function.");
+        };
+
+        object_env.push_scope();
+        object_env.define(String::from("this"), Value::Instance(instance));
+
+        let is_init: bool = is_init || name == "init";
+        LoxCallable::Closure(name, params, body, object_env, is_init, doc)
     }
 }
 
@@ -70,7 +86,56 @@ impl Display for LoxCallable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Native(name, _, _) => write!(f, "{name}"),
-            Self::Closure(name, _, _, _, _) => write!(f, "{name}")
+            Self::Closure(name, _, _, _, _, _) => write!(f, "{name}"),
+            Self::Compiled(closure, _) => write!(f, "{}", closure.function.name)
+        }
+    }
+}
+
+impl Trace for LoxCallable {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match self {
+            Self::Native(_, _, _) => (),
+            Self::Closure(_, _, _, env, _, _) => env.trace(edges),
+            Self::Compiled(closure, receiver) => {
+                edges.push(address(closure));
+
+                if let Some(receiver) = receiver {
+                    edges.push(address(receiver));
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// A function implemented in Rust that can be called from Lox.
+///
+/// Native functions are reference counted closures, so they can capture state from the host program. They are given
+/// a context through which they can call back into the interpreter.
+#[derive(Clone)]
+pub struct NativeFn(Rc<NativeSignature>);
+
+/// The signature shared by all native functions.
+type NativeSignature = dyn Fn(&mut NativeContext, Vec<Value>) -> Result<Value, LoxError>;
+
+impl NativeFn {
+    pub fn new(func: impl Fn(&mut NativeContext, Vec<Value>) -> Result<Value, LoxError> + 'static) -> Self {
+        Self(Rc::new(func))
+    }
+
+    pub fn call(&self, ctx: &mut NativeContext, arg_values: Vec<Value>) -> Result<Value, LoxError> {
+        (self.0)(ctx, arg_values)
+    }
+}
+
+impl Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -1,17 +1,27 @@
-use std::{collections::HashMap, fmt::{self, Display}, rc::Rc};
-use crate::{interpreter::environment::Environment, types::{token::Token, token_type::TokenType, values::{Value, callable::LoxCallable}}};
+use std::{cell::RefCell, collections::HashMap, fmt::{self, Display}, rc::Rc};
+use crate::{interpreter::{environment::Environment, gc::{Trace, address}}, types::{token::Token, token_type::TokenType, values::{Value, callable::LoxCallable}}};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct LoxClass {
     name: String,
     superclass: Option<Rc<LoxClass>>,
     methods: HashMap<String, LoxCallable>,
-    env: Environment
+    getters: HashMap<String, LoxCallable>,
+    setters: HashMap<String, LoxCallable>,
+    // Static methods and fields, which are shared by the class and its subclasses
+    statics: RefCell<HashMap<String, Value>>,
+    env: Environment,
+    doc: Option<String>
 }
 
 impl LoxClass {
-    pub fn new(name: String, superclass: Option<Rc<LoxClass>>, methods: HashMap<String, LoxCallable>, env: Environment) -> Self {
-        Self { name, superclass, methods, env }
+    pub fn new(name: String, superclass: Option<Rc<LoxClass>>, methods: HashMap<String, LoxCallable>, getters: HashMap<String, LoxCallable>, setters: HashMap<String, LoxCallable>, env: Environment, doc: Option<String>) -> Self {
+        Self { name, superclass, methods, getters, setters, statics: RefCell::new(HashMap::new()), env, doc }
+    }
+
+    /// Returns the doc comment written before the class declaration.
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
     }
 
     pub fn find_method(&self, name: &String) -> Option<LoxCallable> {
@@ -26,6 +36,61 @@ impl LoxClass {
         }
     }
 
+    /// Finds the getter of a property, searching the superclasses if the class does not define it.
+    pub fn find_getter(&self, name: &str) -> Option<LoxCallable> {
+        match self.getters.get(name) {
+            Some(getter) => Some(getter.clone()),
+            None => self.superclass.as_ref().and_then(|class| class.find_getter(name))
+        }
+    }
+
+    /// Finds the setter of a property, searching the superclasses if the class does not define it.
+    pub fn find_setter(&self, name: &str) -> Option<LoxCallable> {
+        match self.setters.get(name) {
+            Some(setter) => Some(setter.clone()),
+            None => self.superclass.as_ref().and_then(|class| class.find_setter(name))
+        }
+    }
+
+    /// Retrieves a static method or field, searching the superclasses if the class does not define it.
+    pub fn get_static(&self, name: &str) -> Option<Value> {
+        match self.statics.borrow().get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.superclass.as_ref().and_then(|class| class.get_static(name))
+        }
+    }
+
+    /// Defines a static method or field on the class itself.
+    pub fn define_static(&self, name: String, value: Value) {
+        self.statics.borrow_mut().insert(name, value);
+    }
+
+    /// Sets a static field. A field inherited from a superclass is set on the superclass, so it stays shared.
+    pub fn set_static(&self, name: String, value: Value) {
+        if !self.statics.borrow().contains_key(&name) && let Some(class) = self.find_static_owner(&name) {
+            return class.set_static(name, value);
+        }
+
+        self.statics.borrow_mut().insert(name, value);
+    }
+
+    /// Drops the static members of a class which is garbage, breaking the cycles they are part of.
+    pub(crate) fn clear_statics(&self) {
+        if let Ok(mut statics) = self.statics.try_borrow_mut() {
+            statics.clear();
+        }
+    }
+
+    /// Finds the superclass which defines a static member.
+    fn find_static_owner(&self, name: &str) -> Option<&Rc<LoxClass>> {
+        let superclass: &Rc<LoxClass> = self.superclass.as_ref()?;
+
+        match superclass.statics.borrow().contains_key(name) {
+            true => Some(superclass),
+            false => superclass.find_static_owner(name)
+        }
+    }
+
     pub fn get_name_token(&self) -> Token {
         Token::new(TokenType::Identifier, self.name.clone(), Value::Nil(), 0)
     }
@@ -35,4 +100,19 @@ impl Display for LoxClass {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.name)
     }
-}
\ No newline at end of file
+}
+
+impl Trace for LoxClass {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        if let Some(superclass) = &self.superclass {
+            edges.push(address(superclass));
+        }
+
+        self.methods.values()
+                    .chain(self.getters.values())
+                    .chain(self.setters.values())
+                    .for_each(|method| method.trace(edges));
+        self.statics.borrow().values().for_each(|value| value.trace(edges));
+        self.env.trace(edges);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+use crate::{LoxError, interpreter::{environment::Environment, gc::Trace, interpret::Resume}, vm::machine::Suspended};
+use crate::types::{statement::Statement, values::{Value, callable::{LoxCallable, NativeFn}}};
+
+/// A call to a function which yields. The function's body runs each time the generator is resumed, until it yields a
+/// value or returns.
+pub struct LoxGenerator {
+    // Taken while the body runs, and dropped once it has finished
+    state: Option<GeneratorState>,
+    done: bool
+}
+
+/// Where a suspended generator continues from, for the backend which runs it.
+pub(crate) enum GeneratorState {
+    // The body, the environment it runs in, and where it resumes, from the innermost statement outwards
+    Walking(Box<Vec<Statement>>, Environment, Vec<Resume>),
+    Compiled(Suspended)
+}
+
+impl LoxGenerator {
+    pub(crate) fn new(state: GeneratorState) -> Self {
+        Self { state: Some(state), done: false }
+    }
+
+    /// Whether the body has finished, by returning or by raising an error.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Takes the state of the generator to resume it. Finished generators have none, while a generator which is
+    /// already running can't be resumed again until it yields.
+    pub(crate) fn start(generator: &Rc<RefCell<LoxGenerator>>) -> Result<Option<GeneratorState>, LoxError> {
+        let mut borrowed = generator.borrow_mut();
+
+        if borrowed.done {
+            return Ok(None);
+        }
+
+        match borrowed.state.take() {
+            Some(state) => Ok(Some(state)),
+            None => Err(LoxError::ValueError(Value::Generator(generator.clone()), String::from("Generator is already running.")))
+        }
+    }
+
+    /// Keeps the state of a body which has yielded, so it can be resumed.
+    pub(crate) fn suspend(&mut self, state: GeneratorState) {
+        self.state = Some(state);
+    }
+
+    /// Marks the body as finished, unless it has yielded.
+    pub(crate) fn finish(&mut self) {
+        self.done = self.state.is_none();
+    }
+
+    /// Drops the state of a generator which is garbage, finishing it.
+    pub(crate) fn clear(&mut self) {
+        self.state = None;
+        self.done = true;
+    }
+
+    /// Gets a property of a generator. `next()` resumes the body, returning the value it yields, or the value it
+    /// returns once it finishes. The yield the body stopped at evaluates to nil, while `send(value)` resumes the body
+    /// in the same way with the yield evaluating to the value. `done` is true once the body has finished.
+    pub fn get(generator: &Rc<RefCell<LoxGenerator>>, name: &str) -> Result<Value, LoxError> {
+        match name {
+            "done" => Ok(Value::Bool(generator.borrow().done)),
+            "next" => {
+                let generator: Rc<RefCell<LoxGenerator>> = generator.clone();
+                let method = NativeFn::new(move |ctx, _| ctx.resume(&generator, Value::Nil()));
+                Ok(Value::Callable(LoxCallable::Native(String::from(name), method, 0)))
+            },
+            "send" => {
+                let generator: Rc<RefCell<LoxGenerator>> = generator.clone();
+                let method = NativeFn::new(move |ctx, mut args| ctx.resume(&generator, args.remove(0)));
+                Ok(Value::Callable(LoxCallable::Native(String::from(name), method, 1)))
+            },
+            _ => Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+        }
+    }
+}
+
+impl fmt::Display for LoxGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<generator>")
+    }
+}
+
+// The suspended body can refer back to the generator, so it is left out.
+impl fmt::Debug for LoxGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// A running generator has taken its state, which is then reachable from the running body instead.
+impl Trace for LoxGenerator {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match &self.state {
+            Some(GeneratorState::Walking(_, env, resume)) => {
+                env.trace(edges);
+                resume.iter().for_each(|resume| resume.trace(edges));
+            },
+            Some(GeneratorState::Compiled(suspended)) => suspended.trace(edges),
+            None => ()
+        }
+    }
+}
+
+// Generators are compared by identity, as each call runs the body separately.
+impl PartialEq for LoxGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
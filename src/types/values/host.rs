@@ -0,0 +1,110 @@
+use std::{cell::RefCell, fmt::{self, Debug, Display}, rc::Rc};
+use crate::{LoxError, interpreter::context::NativeContext, types::values::{Value, callable::{LoxCallable, NativeFn}}};
+
+/// A Rust value which can be handed to Lox scripts as an object.
+///
+/// Host objects behave like instances: their properties are read and written with `object.property`, and their
+/// methods are dispatched back to Rust when called.
+pub trait HostObject {
+    /// The name of the object's type, used when the object is printed.
+    fn type_name(&self) -> String;
+
+    /// Retrieves a property of the object, returning None if it does not exist.
+    fn get(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Sets a property of the object.
+    fn set(&mut self, name: &str, _value: Value) -> Result<(), LoxError> {
+        Err(LoxError::NameError(String::from(name), format!("Property {name} cannot be set on {}.", self.type_name())))
+    }
+
+    /// Returns the arity of a method of the object, or None if the method does not exist.
+    fn method_arity(&self, _name: &str) -> Option<usize> {
+        None
+    }
+
+    /// Calls a method of the object. This is only called for methods which have an arity.
+    fn call_method(&mut self, name: &str, _ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+        Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+    }
+}
+
+/// A shared reference to a host object.
+#[derive(Clone)]
+pub struct LoxHost(Rc<RefCell<dyn HostObject>>);
+
+impl LoxHost {
+    pub fn new(object: impl HostObject + 'static) -> Self {
+        Self(Rc::new(RefCell::new(object)))
+    }
+
+    /// Returns the shared object, so the host program can keep access to it.
+    pub fn get_ref(&self) -> Rc<RefCell<dyn HostObject>> {
+        self.0.clone()
+    }
+
+    /// Gets a property from the object, binding it to a native function if the property is a method.
+    pub fn get(&self, name: &str) -> Result<Value, LoxError> {
+        let object = self.borrow()?;
+
+        if let Some(value) = object.get(name) {
+            return Ok(value);
+        }
+
+        match object.method_arity(name) {
+            Some(arity) => {
+                let host: LoxHost = self.clone();
+                let method_name: String = String::from(name);
+                let method = NativeFn::new(move |ctx, args| host.call_method(&method_name, ctx, args));
+                Ok(Value::Callable(LoxCallable::Native(String::from(name), method, arity)))
+            },
+            None => Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+        }
+    }
+
+    /// Sets a property of the object.
+    pub fn set(&self, name: &str, value: Value) -> Result<(), LoxError> {
+        match self.0.try_borrow_mut() {
+            Ok(mut object) => object.set(name, value),
+            Err(_) => Err(self.borrow_error())
+        }
+    }
+
+    fn call_method(&self, name: &str, ctx: &mut NativeContext, args: Vec<Value>) -> Result<Value, LoxError> {
+        match self.0.try_borrow_mut() {
+            Ok(mut object) => object.call_method(name, ctx, args),
+            Err(_) => Err(self.borrow_error())
+        }
+    }
+
+    fn borrow(&self) -> Result<std::cell::Ref<'_, dyn HostObject>, LoxError> {
+        self.0.try_borrow().map_err(|_| self.borrow_error())
+    }
+
+    /// The object is already borrowed mutably if one of its methods calls back into Lox, which then uses the object.
+    fn borrow_error(&self) -> LoxError {
+        LoxError::ValueError(Value::Host(self.clone()), String::from("Object is in use by one of its own methods."))
+    }
+}
+
+impl Debug for LoxHost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<host object>")
+    }
+}
+
+impl PartialEq for LoxHost {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Display for LoxHost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.try_borrow() {
+            Ok(object) => write!(f, "Instance of {}", object.type_name()),
+            Err(_) => write!(f, "Instance of host object")
+        }
+    }
+}
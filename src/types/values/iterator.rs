@@ -0,0 +1,105 @@
+use std::{cell::RefCell, fmt, rc::Rc, vec};
+use crate::{LoxError, interpreter::gc::{Heap, Trace, address}, types::values::{Value, callable::{LoxCallable, NativeFn}, list::LoxList}};
+
+/// The state of a loop over a built-in value. Ranges are iterators from the start, so they count lazily.
+#[derive(Debug)]
+pub enum LoxIterator {
+    // Lists are walked by index, so items pushed during the loop are reached
+    List(Rc<RefCell<LoxList>>, usize),
+    // Values collected when the loop starts, such as the keys of a map or the characters of a string
+    Values(vec::IntoIter<Value>),
+    // The next number, the end it stops before, and the step between numbers
+    Range(i64, i64, i64)
+}
+
+impl LoxIterator {
+    /// Creates an iterator over a list, map or string. Iterators and generators are iterated over as they are.
+    pub fn over(value: Value, heap: &Heap) -> Result<Value, LoxError> {
+        let iterator: LoxIterator = match value {
+            Value::List(list) => LoxIterator::List(list, 0),
+            Value::Map(ref map) => LoxIterator::Values(map.borrow().keys().into_iter()),
+            Value::Str(ref string) => {
+                let chars: Vec<Value> = string.chars().map(|c| Value::Str(c.to_string())).collect();
+                LoxIterator::Values(chars.into_iter())
+            },
+            Value::Iterator(_) | Value::Generator(_) => return Ok(value),
+            _ => return Err(LoxError::ValueError(value, String::from("Value is not iterable.")))
+        };
+
+        Ok(Value::Iterator(heap.iterator(iterator)))
+    }
+
+    /// Creates a range from a start up to, but not including, an end. The range counts down if the end is lower.
+    pub fn range(start: i64, end: i64) -> Value {
+        let step: i64 = match end >= start {
+            true => 1,
+            false => -1
+        };
+
+        Value::Iterator(Rc::new(RefCell::new(LoxIterator::Range(start, end, step))))
+    }
+
+    /// Gets a built-in method of an iterator, bound to the iterator. `next()` returns nil once the iterator is done.
+    pub fn get_method(iterator: &Rc<RefCell<LoxIterator>>, name: &str) -> Result<Value, LoxError> {
+        if name != "next" {
+            return Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")));
+        }
+
+        let iterator: Rc<RefCell<LoxIterator>> = iterator.clone();
+        let method = NativeFn::new(move |_, _| Ok(iterator.borrow_mut().next().unwrap_or(Value::Nil())));
+        Ok(Value::Callable(LoxCallable::Native(String::from(name), method, 0)))
+    }
+}
+
+impl Iterator for LoxIterator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            Self::List(list, index) => {
+                let item: Value = list.borrow().items.get(*index)?.clone();
+                *index += 1;
+                Some(item)
+            },
+            Self::Values(values) => values.next(),
+            Self::Range(next, end, step) => {
+                let done: bool = match *step > 0 {
+                    true => *next >= *end,
+                    false => *next <= *end
+                };
+
+                if done {
+                    return None;
+                }
+
+                let value: i64 = *next;
+                *next += *step;
+                Some(Value::Int(value))
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoxIterator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<iterator>")
+    }
+}
+
+// A list can hold an iterator over itself, so the list is traced.
+impl Trace for LoxIterator {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match self {
+            Self::List(list, _) => edges.push(address(list)),
+            Self::Values(values) => values.as_slice().iter().for_each(|value| value.trace(edges)),
+            Self::Range(..) => ()
+        }
+    }
+}
+
+// Iterators are compared by identity, as advancing one does not advance the other.
+impl PartialEq for LoxIterator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
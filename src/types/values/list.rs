@@ -0,0 +1,129 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+use crate::{LoxError, interpreter::{context::NativeContext, gc::Trace}, types::values::{Value, callable::{LoxCallable, NativeFn}}};
+
+/// A growable list of values. Lists are shared, so every reference to a list sees the changes made through the others.
+#[derive(Debug, Default)]
+pub struct LoxList {
+    pub items: Vec<Value>
+}
+
+/// The signature of the built-in list methods.
+type ListMethod = fn(&Rc<RefCell<LoxList>>, &mut NativeContext, Vec<Value>) -> Result<Value, LoxError>;
+
+impl LoxList {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self { items }
+    }
+
+    /// Retrieves the item at an index.
+    pub fn get(&self, index: &Value) -> Result<Value, LoxError> {
+        let position: usize = self.position(index, self.items.len())?;
+        Ok(self.items[position].clone())
+    }
+
+    /// Replaces the item at an index.
+    pub fn set(&mut self, index: &Value, value: Value) -> Result<(), LoxError> {
+        let position: usize = self.position(index, self.items.len())?;
+        self.items[position] = value;
+        Ok(())
+    }
+
+    /// Gets a built-in method of a list, bound to the list.
+    pub fn get_method(list: &Rc<RefCell<LoxList>>, name: &str) -> Result<Value, LoxError> {
+        let (method, arity): (ListMethod, usize) = match name {
+            "push" => (push, 1),
+            "pop" => (pop, 0),
+            "len" => (len, 0),
+            "insert" => (insert, 2),
+            "remove" => (remove, 1),
+            "slice" => (slice, 2),
+            _ => return Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+        };
+
+        let list: Rc<RefCell<LoxList>> = list.clone();
+        let method = NativeFn::new(move |ctx, args| method(&list, ctx, args));
+        Ok(Value::Callable(LoxCallable::Native(String::from(name), method, arity)))
+    }
+
+    /// Converts an index to a position in the list, which must be below a limit.
+    fn position(&self, index: &Value, limit: usize) -> Result<usize, LoxError> {
+        match index {
+            Value::Int(position) if *position >= 0 && (*position as usize) < limit => Ok(*position as usize),
+            Value::Int(_) => Err(LoxError::IndexError(index.clone(), String::from("List index out of range."))),
+            _ => Err(LoxError::ValueError(index.clone(), String::from("List index must be an integer.")))
+        }
+    }
+
+    /// Writes the list, showing collections which contain themselves where they repeat.
+    pub(crate) fn write(&self, f: &mut fmt::Formatter, seen: &mut Vec<usize>) -> fmt::Result {
+        write!(f, "[")?;
+
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            item.write_nested(f, seen)?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+/// Appends an item to the end of the list.
+fn push(list: &Rc<RefCell<LoxList>>, _ctx: &mut NativeContext, mut args: Vec<Value>) -> Result<Value, LoxError> {
+    list.borrow_mut().items.push(args.remove(0));
+    Ok(Value::Nil())
+}
+
+/// Removes and returns the last item of the list.
+fn pop(list: &Rc<RefCell<LoxList>>, _ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+    let item: Option<Value> = list.borrow_mut().items.pop();
+    item.ok_or_else(|| LoxError::ValueError(Value::List(list.clone()), String::from("Can't pop from an empty list.")))
+}
+
+/// Returns the number of items in the list.
+fn len(list: &Rc<RefCell<LoxList>>, _ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+    Ok(Value::Int(list.borrow().items.len() as i64))
+}
+
+/// Inserts an item before an index. Inserting at the length of the list appends the item.
+fn insert(list: &Rc<RefCell<LoxList>>, _ctx: &mut NativeContext, mut args: Vec<Value>) -> Result<Value, LoxError> {
+    let item: Value = args.remove(1);
+    let mut list = list.borrow_mut();
+    let position: usize = list.position(&args[0], list.items.len() + 1)?;
+    list.items.insert(position, item);
+    Ok(Value::Nil())
+}
+
+/// Removes and returns the item at an index.
+fn remove(list: &Rc<RefCell<LoxList>>, _ctx: &mut NativeContext, args: Vec<Value>) -> Result<Value, LoxError> {
+    let mut list = list.borrow_mut();
+    let position: usize = list.position(&args[0], list.items.len())?;
+    Ok(list.items.remove(position))
+}
+
+/// Returns a new list of the items from a start index up to, but not including, an end index.
+fn slice(list: &Rc<RefCell<LoxList>>, ctx: &mut NativeContext, args: Vec<Value>) -> Result<Value, LoxError> {
+    let items: Vec<Value> = {
+        let list = list.borrow();
+        let end: usize = list.position(&args[1], list.items.len() + 1)?;
+        let start: usize = list.position(&args[0], end + 1)?;
+        list.items[start..end].to_vec()
+    };
+
+    Ok(Value::List(ctx.heap().list(LoxList::new(items))))
+}
+
+// Lists are compared by identity, as they can contain themselves.
+impl PartialEq for LoxList {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Trace for LoxList {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        self.items.iter().for_each(|item| item.trace(edges));
+    }
+}
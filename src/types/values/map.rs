@@ -0,0 +1,183 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+use crate::{LoxError, interpreter::{context::NativeContext, gc::Trace}, types::values::{Value, callable::{LoxCallable, NativeFn}, list::LoxList}};
+
+/// A map from keys to values, which keeps its entries in the order they were first inserted. Maps are shared like
+/// lists.
+#[derive(Debug, Default)]
+pub struct LoxMap {
+    // The keys are kept as they were written, so they are returned unchanged
+    entries: Vec<(Value, Value)>,
+    index: HashMap<MapKey, usize>
+}
+
+/// The hashable form of a map key. Keys which are equal with `==` have the same form, so an integer and a float with
+/// the same value are the same key.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum MapKey {
+    Str(String),
+    Int(i64),
+    // The bits of a float without an integer value
+    Float(u64),
+    Bool(bool),
+    Nil
+}
+
+/// The signature of the built-in map methods.
+type MapMethod = fn(&Rc<RefCell<LoxMap>>, &mut NativeContext, Vec<Value>) -> Result<Value, LoxError>;
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieves the value of a key.
+    pub fn get(&self, key: &Value) -> Result<Value, LoxError> {
+        match self.index.get(&MapKey::new(key)?) {
+            Some(position) => Ok(self.entries[*position].1.clone()),
+            None => Err(LoxError::IndexError(key.clone(), String::from("Key is not in the map.")))
+        }
+    }
+
+    /// Sets the value of a key, adding the key if it is not in the map yet.
+    pub fn set(&mut self, key: Value, value: Value) -> Result<(), LoxError> {
+        match self.index.get(&MapKey::new(&key)?) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.index.insert(MapKey::new(&key)?, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a key is in the map.
+    pub fn has(&self, key: &Value) -> Result<bool, LoxError> {
+        Ok(self.index.contains_key(&MapKey::new(key)?))
+    }
+
+    /// Removes a key from the map, returning its value.
+    pub fn remove(&mut self, key: &Value) -> Result<Value, LoxError> {
+        let position: usize = match self.index.remove(&MapKey::new(key)?) {
+            Some(position) => position,
+            None => return Err(LoxError::IndexError(key.clone(), String::from("Key is not in the map.")))
+        };
+
+        // The entries after the removed one move down
+        self.index.values_mut().filter(|other| **other > position).for_each(|other| *other -= 1);
+        Ok(self.entries.remove(position).1)
+    }
+
+    /// Returns the keys of the map, in insertion order.
+    pub fn keys(&self) -> Vec<Value> {
+        self.entries.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    /// Returns the values of the map, in the order of their keys.
+    pub fn values(&self) -> Vec<Value> {
+        self.entries.iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every entry of the map.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// Gets a built-in method of a map, bound to the map.
+    pub fn get_method(map: &Rc<RefCell<LoxMap>>, name: &str) -> Result<Value, LoxError> {
+        let (method, arity): (MapMethod, usize) = match name {
+            "keys" => (keys, 0),
+            "values" => (values, 0),
+            "has" => (has, 1),
+            "remove" => (remove, 1),
+            "len" => (len, 0),
+            _ => return Err(LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+        };
+
+        let map: Rc<RefCell<LoxMap>> = map.clone();
+        let method = NativeFn::new(move |ctx, args| method(&map, ctx, args));
+        Ok(Value::Callable(LoxCallable::Native(String::from(name), method, arity)))
+    }
+
+    /// Writes the map, showing collections which contain themselves where they repeat.
+    pub(crate) fn write(&self, f: &mut fmt::Formatter, seen: &mut Vec<usize>) -> fmt::Result {
+        write!(f, "{{")?;
+
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{key}: ")?;
+            value.write_nested(f, seen)?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+impl MapKey {
+    fn new(key: &Value) -> Result<Self, LoxError> {
+        match key {
+            Value::Str(string) => Ok(Self::Str(string.clone())),
+            Value::Int(int) => Ok(Self::Int(*int)),
+            Value::Float(float) if float.is_nan() => Err(LoxError::ValueError(key.clone(), String::from("NaN can't be used as a map key."))),
+            // Floats with an integer value equal that integer, and zero equals negative zero
+            Value::Float(float) if float.fract() == 0.0 && *float >= i64::MIN as f64 && *float < i64::MAX as f64 => Ok(Self::Int(*float as i64)),
+            Value::Float(float) => Ok(Self::Float(float.to_bits())),
+            Value::Bool(bool) => Ok(Self::Bool(*bool)),
+            Value::Nil() => Ok(Self::Nil),
+            _ => Err(LoxError::ValueError(key.clone(), String::from("Value can't be used as a map key.")))
+        }
+    }
+}
+
+/// Returns a list of the keys of the map.
+fn keys(map: &Rc<RefCell<LoxMap>>, ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+    let keys: Vec<Value> = map.borrow().keys();
+    Ok(Value::List(ctx.heap().list(LoxList::new(keys))))
+}
+
+/// Returns a list of the values of the map.
+fn values(map: &Rc<RefCell<LoxMap>>, ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+    let values: Vec<Value> = map.borrow().values();
+    Ok(Value::List(ctx.heap().list(LoxList::new(values))))
+}
+
+/// Checks if a key is in the map.
+fn has(map: &Rc<RefCell<LoxMap>>, _ctx: &mut NativeContext, args: Vec<Value>) -> Result<Value, LoxError> {
+    Ok(Value::Bool(map.borrow().has(&args[0])?))
+}
+
+/// Removes a key from the map, returning its value.
+fn remove(map: &Rc<RefCell<LoxMap>>, _ctx: &mut NativeContext, args: Vec<Value>) -> Result<Value, LoxError> {
+    map.borrow_mut().remove(&args[0])
+}
+
+/// Returns the number of entries in the map.
+fn len(map: &Rc<RefCell<LoxMap>>, _ctx: &mut NativeContext, _args: Vec<Value>) -> Result<Value, LoxError> {
+    Ok(Value::Int(map.borrow().len() as i64))
+}
+
+// Maps are compared by identity, as they can contain themselves.
+impl PartialEq for LoxMap {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Keys can't refer to tracked objects, so only values are traced.
+impl Trace for LoxMap {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        self.entries.iter().for_each(|(_, value)| value.trace(edges));
+    }
+}
@@ -2,12 +2,19 @@ use std::cell::{Ref, RefCell};
 use std::fmt;
 use std::rc::Rc;
 use enum_as_inner::EnumAsInner;
+use crate::interpreter::gc::{Trace, address};
 use crate::types::values::object::LoxObject;
-use crate::types::values::{callable::LoxCallable, class::LoxClass};
+use crate::types::values::{callable::LoxCallable, class::LoxClass, generator::LoxGenerator, host::LoxHost, iterator::LoxIterator, list::LoxList, map::LoxMap, module::LoxModule};
 use crate::types::token::Token;
 
 pub mod callable;
 pub mod class;
+pub mod generator;
+pub mod host;
+pub mod iterator;
+pub mod list;
+pub mod map;
+pub mod module;
 pub mod object;
 
 #[derive(Clone, Debug, PartialEq, EnumAsInner)]
@@ -20,6 +27,12 @@ pub enum Value {
     Callable(LoxCallable),
     Class(Rc<LoxClass>),
     Instance(Rc<RefCell<LoxObject>>),
+    List(Rc<RefCell<LoxList>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Iterator(Rc<RefCell<LoxIterator>>),
+    Generator(Rc<RefCell<LoxGenerator>>),
+    Module(Rc<LoxModule>),
+    Host(LoxHost),
     Nil(),
     None()
 }
@@ -38,8 +51,56 @@ impl fmt::Display for Value {
                 let obj: Ref<LoxObject> = obj.borrow();
                 obj.fmt(f)
             },
+            Self::List(_) | Self::Map(_) => self.write_nested(f, &mut Vec::new()),
+            Self::Iterator(iterator) => iterator.borrow().fmt(f),
+            Self::Generator(generator) => generator.borrow().fmt(f),
+            Self::Module(module) => module.fmt(f),
+            Self::Host(host) => host.fmt(f),
             Self::Nil() => write!(f, ""),
             Self::None() => write!(f, "")
         }
     }
-}
\ No newline at end of file
+}
+
+impl Value {
+    /// Writes a value which may be inside a collection. Collections which contain themselves are shown as `[...]` or
+    /// `{...}` where they repeat.
+    pub(crate) fn write_nested(&self, f: &mut fmt::Formatter, seen: &mut Vec<usize>) -> fmt::Result {
+        let (collection, repeated): (usize, &str) = match self {
+            Self::List(list) => (address(list), "[...]"),
+            Self::Map(map) => (address(map), "{...}"),
+            _ => return write!(f, "{self}")
+        };
+
+        if seen.contains(&collection) {
+            return write!(f, "{repeated}");
+        }
+
+        seen.push(collection);
+
+        let result: fmt::Result = match self {
+            Self::List(list) => list.borrow().write(f, seen),
+            Self::Map(map) => map.borrow().write(f, seen),
+            _ => Ok(())
+        };
+
+        seen.pop();
+        result
+    }
+}
+
+// Host objects and native functions are opaque, so whatever they refer to is kept alive.
+impl Trace for Value {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        match self {
+            Self::Callable(callable) => callable.trace(edges),
+            Self::Class(class) => edges.push(address(class)),
+            Self::Instance(obj) => edges.push(address(obj)),
+            Self::List(list) => edges.push(address(list)),
+            Self::Map(map) => edges.push(address(map)),
+            Self::Iterator(iterator) => edges.push(address(iterator)),
+            Self::Generator(generator) => edges.push(address(generator)),
+            _ => ()
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+use crate::{LoxError, interpreter::environment::Environment, types::values::Value};
+
+/// A module imported from another file. The globals the module defined are read as its properties.
+pub struct LoxModule {
+    pub name: String,
+    env: Environment
+}
+
+impl LoxModule {
+    pub fn new(name: String, env: Environment) -> Self {
+        Self { name, env }
+    }
+
+    /// Retrieves a global defined by the module.
+    pub fn get(&self, name: &str) -> Result<Value, LoxError> {
+        self.env.get_global(name)
+                .map_err(|_| LoxError::NameError(String::from(name), format!("Undefined property {name}")))
+    }
+}
+
+impl fmt::Display for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+// The module's globals can refer back to the module, so they are left out.
+impl fmt::Debug for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// Modules are compared by identity, as each file is only loaded once.
+impl PartialEq for LoxModule {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -1,5 +1,5 @@
 use std::{collections::HashMap, fmt::{self, Display}, rc::Rc};
-use crate::{types::{token::Token, values::{Value, class::LoxClass}}};
+use crate::{interpreter::gc::{Trace, address}, types::{token::Token, values::{Value, class::LoxClass}}};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct LoxObject {
@@ -25,4 +25,11 @@ impl Display for LoxObject {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Instance of {}", self.class)
     }
-}
\ No newline at end of file
+}
+
+impl Trace for LoxObject {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        edges.push(address(&self.class));
+        self.fields.values().for_each(|value| value.trace(edges));
+    }
+}
@@ -0,0 +1,135 @@
+use std::rc::Rc;
+use crate::types::{token_type::TokenType, values::Value};
+use crate::vm::function::Function;
+
+/// A single bytecode instruction. Operands index into the chunk's tables, the stack slots of the current call, or the
+/// upvalues of the current closure. Jump targets are absolute instruction indices.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OpCode {
+    Constant(u32),
+    Nil,
+    True,
+    False,
+    Pop,
+    // Pushes copies of a number of values from the top of the stack, keeping their order
+    Duplicate(u32),
+    GetLocal(u32),
+    SetLocal(u32),
+    GetGlobal(u32),
+    DefineGlobal(u32),
+    SetGlobal(u32),
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+    GetProperty(u32),
+    SetProperty(u32),
+    GetSuper(u32),
+    List(u32),
+    Map(u32),
+    GetIndex,
+    SetIndex,
+    Unary(TokenType),
+    Binary(TokenType),
+    Logical(TokenType),
+    // Replaces the value on top of the stack with its display form
+    Stringify,
+    Print,
+    Jump(u32),
+    JumpIfFalse(u32),
+    // Replaces the value on top of the stack with an iterator over it
+    Iterate,
+    // Pushes the next item of the iterator on top of the stack, or jumps once the iterator is done
+    ForNext(u32),
+    Call(u32),
+    Closure(u32),
+    CloseUpvalue,
+    Return,
+    // Suspends the generator running in the current frame, handing it the value on top of the stack. The value the
+    // generator is resumed with is pushed in its place
+    Yield,
+    // Sets up a handler which jumps to a catch clause or finally block when an error is raised
+    PushCatch(u32),
+    PushFinally(u32),
+    PopHandler,
+    Throw,
+    // Raises the error a finally block was entered with again, if there is one
+    EndFinally,
+    Class(u32),
+    // Adds the value on top of the stack to the class below it as a static member
+    DefineStatic(u32),
+    // Pushes the module at a path, loading it the first time it is imported
+    Import(u32)
+}
+
+/// The compiled body of a function, along with the tables its instructions refer to.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    pub constants: Vec<Value>,
+    pub functions: Vec<Rc<Function>>,
+    pub classes: Vec<ClassProto>
+}
+
+/// The shape of a class declaration. The class is built at runtime from the method, getter and setter closures on top
+/// of the stack, and the superclass below them if it has one.
+#[derive(Debug)]
+pub struct ClassProto {
+    pub name: String,
+    pub methods: usize,
+    pub getters: usize,
+    pub setters: usize,
+    pub inherits: bool,
+    pub doc: Option<String>
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an instruction, returning its index.
+    pub fn write(&mut self, op: OpCode) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    /// Adds a value to the constants table, returning its index. Strings are shared, as every use of a name adds it.
+    pub fn add_constant(&mut self, value: Value) -> u32 {
+        if let Value::Str(_) = value && let Some(index) = self.constants.iter().position(|constant| constant == &value) {
+            return index as u32;
+        }
+
+        self.constants.push(value);
+        (self.constants.len() - 1) as u32
+    }
+
+    pub fn add_function(&mut self, function: Function) -> u32 {
+        self.functions.push(Rc::new(function));
+        (self.functions.len() - 1) as u32
+    }
+
+    pub fn add_class(&mut self, class: ClassProto) -> u32 {
+        self.classes.push(class);
+        (self.classes.len() - 1) as u32
+    }
+
+    /// Returns the index the next instruction will be written to.
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    /// Points a previously written jump at a target instruction.
+    pub fn patch_jump(&mut self, jump: usize, target: usize) {
+        self.code[jump] = match self.code[jump] {
+            OpCode::Jump(_) => OpCode::Jump(target as u32),
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target as u32),
+            OpCode::ForNext(_) => OpCode::ForNext(target as u32),
+            OpCode::PushCatch(_) => OpCode::PushCatch(target as u32),
+            OpCode::PushFinally(_) => OpCode::PushFinally(target as u32),
+            op => op
+        };
+    }
+}
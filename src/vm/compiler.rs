@@ -0,0 +1,815 @@
+use crate::LoxError;
+use ternop::ternary;
+use crate::types::{expr::Expr, statement::Statement, token::Token, token_type::TokenType, values::Value};
+use crate::vm::{chunk::{Chunk, ClassProto, OpCode}, function::{Function, UpvalueIndex}};
+
+/// Compiles a resolved program to bytecode for the VM.
+///
+/// Variables are resolved by name as they are compiled. Locals live in the stack slots of the call they belong to,
+/// variables captured from enclosing functions become upvalues, and every other variable is a global.
+pub struct Compiler {
+    // The functions being compiled, from the top level script to the innermost function
+    functions: Vec<FunctionState>
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer
+}
+
+struct FunctionState {
+    function: Function,
+    kind: FunctionKind,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryBlock>
+}
+
+/// A loop being compiled, with the jumps out of it which are patched once the loop is compiled.
+struct Loop {
+    scope_depth: usize,
+    // The number of try statements the loop is inside of
+    tries: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>
+}
+
+/// A try statement whose handlers are set up while its body or catch clause is compiled. Jumps out of it remove the
+/// handlers and run the finally block.
+#[derive(Clone)]
+struct TryBlock {
+    handlers: usize,
+    // The number of locals declared before the try statement
+    locals: usize,
+    finally: Option<Box<Vec<Statement>>>
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self { functions: Vec::new() }
+    }
+
+    /// Compiles a program into the function that runs it. If the program ends with an expression statement, the
+    /// function returns the value of that expression.
+    pub fn compile(&mut self, statements: &[Statement]) -> Result<Function, LoxError> {
+        self.functions.clear();
+        self.begin_function(String::from("script"), 0, FunctionKind::Script);
+
+        match statements.split_last() {
+            Some((Statement::Expression(expr), rest)) => {
+                self.statements(rest)?;
+                self.expression(expr)?;
+                self.emit(OpCode::Return);
+            },
+            _ => {
+                self.statements(statements)?;
+                self.emit_return();
+            }
+        }
+
+        Ok(self.end_function())
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), LoxError> {
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compiles each type of statement
+    fn statement(&mut self, stmt: &Statement) -> Result<(), LoxError> {
+        match stmt {
+            Statement::Block(statements) => {
+                self.begin_scope();
+                self.statements(statements)?;
+                self.end_scope();
+            },
+            Statement::Break(_) => {
+                let jump: usize = self.loop_jump()?;
+                self.current_loop().breaks.push(jump);
+            },
+            Statement::Class(name, superclass, methods, accessors, statics, doc) => {
+                self.class(name, superclass, methods, accessors, statics, doc)?
+            },
+            Statement::Continue(_) => {
+                let jump: usize = self.loop_jump()?;
+                self.current_loop().continues.push(jump);
+            },
+            Statement::FunDeclaration(name, params, body, doc) => {
+                if self.is_global_scope() {
+                    self.function(name, params, body, doc, FunctionKind::Function)?;
+                    self.define_variable(&name.lexeme);
+                }
+
+                // Locals are declared first, so the function can capture itself
+                else {
+                    self.add_local(name.lexeme.clone());
+                    self.function(name, params, body, doc, FunctionKind::Function)?;
+                }
+            },
+            Statement::Expression(expr) => {
+                self.expression(expr)?;
+                self.emit(OpCode::Pop);
+            },
+            Statement::ForIn(name, iterable, body) => self.for_in(name, iterable, body)?,
+            Statement::FromImport(path, names) => {
+                // The module is only loaded once, so importing it again for each name just looks it up
+                for name in names {
+                    let module: u32 = self.name_constant(&path.literal.to_string());
+                    self.emit(OpCode::Import(module));
+                    let property: u32 = self.name_constant(&name.lexeme);
+                    self.emit(OpCode::GetProperty(property));
+                    self.define_variable(&name.lexeme);
+                }
+            },
+            Statement::Generator(body) => {
+                self.current_mut().function.is_generator = true;
+                self.statements(body)?;
+            },
+            Statement::If(cond, then, els) => {
+                self.expression(cond)?;
+                let then_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                self.emit(OpCode::Pop);
+                self.branch(then)?;
+
+                let else_jump: usize = self.emit(OpCode::Jump(0));
+                self.patch_jump(then_jump);
+                self.emit(OpCode::Pop);
+
+                if let Some(els) = &**els {
+                    self.branch(els)?;
+                }
+
+                self.patch_jump(else_jump);
+            },
+            Statement::Import(path, name) => {
+                let module: u32 = self.name_constant(&path.literal.to_string());
+                self.emit(OpCode::Import(module));
+                self.define_variable(&name.lexeme);
+            },
+            Statement::Print(expr) => {
+                self.expression(expr)?;
+                self.emit(OpCode::Print);
+            },
+            Statement::Return(_, expr) => {
+                // Initializers always return their instance
+                if self.current().kind == FunctionKind::Initializer {
+                    self.emit(OpCode::GetLocal(0));
+                }
+
+                else {
+                    self.expression(expr)?;
+                }
+
+                // The returned value keeps its slot while finally blocks run
+                if !self.current().tries.is_empty() {
+                    self.add_local(String::new());
+                    self.leave_tries(0)?;
+                    self.current_mut().locals.pop();
+                }
+
+                self.emit(OpCode::Return);
+            },
+            Statement::Throw(_, expr) => {
+                self.expression(expr)?;
+                self.emit(OpCode::Throw);
+            },
+            Statement::Try(body, catch, finally) => self.try_statement(body, catch, finally)?,
+            Statement::Var(name, initializer) => {
+                match initializer {
+                    Some(expr) => self.expression(expr)?,
+                    None => { self.emit(OpCode::Nil); }
+                }
+
+                self.define_variable(&name.lexeme);
+            },
+            Statement::While(cond, body, increment) => {
+                let loop_start: usize = self.chunk().len();
+                self.expression(cond)?;
+                let exit_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                self.emit(OpCode::Pop);
+
+                let (scope_depth, tries): (usize, usize) = (self.current().scope_depth, self.current().tries.len());
+                self.current_mut().loops.push(Loop { scope_depth, tries, breaks: Vec::new(), continues: Vec::new() });
+                self.branch(body)?;
+                let compiled: Loop = self.current_mut().loops.pop().expect("No loop is being compiled.");
+
+                // Continuing skips the rest of the body, but still runs the increment
+                for jump in compiled.continues {
+                    self.patch_jump(jump);
+                }
+
+                if let Some(increment) = increment {
+                    self.expression(increment)?;
+                    self.emit(OpCode::Pop);
+                }
+
+                self.emit(OpCode::Jump(loop_start as u32));
+
+                self.patch_jump(exit_jump);
+                self.emit(OpCode::Pop);
+
+                for jump in compiled.breaks {
+                    self.patch_jump(jump);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the body of an if statement or a loop. A local declaration used as the body is given its own scope, so
+    /// skipping or repeating the body does not unbalance the stack slots of the enclosing scope.
+    fn branch(&mut self, stmt: &Statement) -> Result<(), LoxError> {
+        let is_declaration: bool = matches!(stmt, Statement::Var(..) | Statement::FunDeclaration(..) | Statement::Class(..));
+        let scoped: bool = is_declaration && !self.is_global_scope();
+
+        if scoped {
+            self.begin_scope();
+        }
+
+        self.statement(stmt)?;
+
+        if scoped {
+            self.end_scope();
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a loop over the items of a value. The iterator is kept in a hidden local while the loop runs, and each
+    /// item is bound to the loop variable in a scope of its own, so closures capture the item of their iteration.
+    fn for_in(&mut self, name: &Token, iterable: &Expr, body: &Statement) -> Result<(), LoxError> {
+        self.begin_scope();
+        self.expression(iterable)?;
+        self.emit(OpCode::Iterate);
+        // A keyword is used as the local's name, so no variable can refer to it
+        self.add_local(String::from("for"));
+
+        let loop_start: usize = self.chunk().len();
+        let exit_jump: usize = self.emit(OpCode::ForNext(0));
+
+        let (scope_depth, tries): (usize, usize) = (self.current().scope_depth, self.current().tries.len());
+        self.current_mut().loops.push(Loop { scope_depth, tries, breaks: Vec::new(), continues: Vec::new() });
+        self.begin_scope();
+        self.add_local(name.lexeme.clone());
+        self.branch(body)?;
+        self.end_scope();
+        let compiled: Loop = self.current_mut().loops.pop().expect("No loop is being compiled.");
+
+        // Continuing has already discarded the loop variable, so it goes straight to the next item
+        for jump in compiled.continues {
+            self.patch_jump(jump);
+        }
+
+        self.emit(OpCode::Jump(loop_start as u32));
+        self.patch_jump(exit_jump);
+
+        for jump in compiled.breaks {
+            self.patch_jump(jump);
+        }
+
+        self.end_scope();
+        Ok(())
+    }
+
+    /// Emits a jump out of the body of the innermost loop, leaving the try statements inside it and discarding the
+    /// locals declared inside it. The locals stay declared, as the rest of the body is still compiled after the jump.
+    fn loop_jump(&mut self) -> Result<usize, LoxError> {
+        let tries: usize = self.current().loops.last().expect("No loop is being compiled.").tries;
+        self.leave_tries(tries)?;
+
+        let state: &FunctionState = self.current();
+        let scope_depth: usize = state.loops.last().expect("No loop is being compiled.").scope_depth;
+        let ops: Vec<OpCode> = state.locals.iter()
+                                           .rev()
+                                           .take_while(|local| local.depth > scope_depth)
+                                           .map(|local| ternary!(local.is_captured, OpCode::CloseUpvalue, OpCode::Pop))
+                                           .collect();
+
+        for op in ops {
+            self.emit(op);
+        }
+
+        Ok(self.emit(OpCode::Jump(0)))
+    }
+
+    fn current_loop(&mut self) -> &mut Loop {
+        self.current_mut().loops.last_mut().expect("No loop is being compiled.")
+    }
+
+    /// Compiles a try statement. The VM jumps to the catch clause or finally block through the handlers set up before
+    /// the body, leaving the error it caught in the slot after the locals declared before the statement.
+    fn try_statement(&mut self, body: &[Statement], catch: &Option<(Token, Box<Vec<Statement>>)>, finally: &Option<Box<Vec<Statement>>>) -> Result<(), LoxError> {
+        let locals: usize = self.current().locals.len();
+        let finally_handler: Option<usize> = finally.as_ref().map(|_| self.emit(OpCode::PushFinally(0)));
+        let catch_handler: Option<usize> = catch.as_ref().map(|_| self.emit(OpCode::PushCatch(0)));
+
+        let handlers: usize = usize::from(finally.is_some()) + usize::from(catch.is_some());
+        self.current_mut().tries.push(TryBlock { handlers, locals, finally: finally.clone() });
+        self.begin_scope();
+        self.statements(body)?;
+        self.end_scope();
+        self.current_mut().tries.pop();
+
+        if let (Some((name, handler)), Some(catch_handler)) = (catch, catch_handler) {
+            self.emit(OpCode::PopHandler);
+            let skip_jump: usize = self.emit(OpCode::Jump(0));
+            self.patch_jump(catch_handler);
+
+            // Only the finally handler is left while the catch clause runs
+            let handlers: usize = usize::from(finally.is_some());
+            self.current_mut().tries.push(TryBlock { handlers, locals, finally: finally.clone() });
+            self.begin_scope();
+            self.add_local(name.lexeme.clone());
+            self.statements(handler)?;
+            self.end_scope();
+            self.current_mut().tries.pop();
+
+            self.patch_jump(skip_jump);
+        }
+
+        if let (Some(finally), Some(finally_handler)) = (finally, finally_handler) {
+            // The block is entered with the error being handled in its first slot, or nil if there is none
+            self.emit(OpCode::PopHandler);
+            self.emit(OpCode::Nil);
+            self.patch_jump(finally_handler);
+
+            self.begin_scope();
+            self.add_local(String::new());
+            self.begin_scope();
+            self.statements(finally)?;
+            self.end_scope();
+            self.emit(OpCode::EndFinally);
+            self.end_scope();
+        }
+
+        Ok(())
+    }
+
+    /// Removes the handlers of the try statements a jump leaves, from the innermost outwards, and runs their finally
+    /// blocks. The locals declared inside a try statement are hidden while its finally block is compiled again.
+    fn leave_tries(&mut self, outermost: usize) -> Result<(), LoxError> {
+        let tries: Vec<TryBlock> = self.current().tries.clone();
+
+        for (index, try_block) in tries.iter().enumerate().skip(outermost).rev() {
+            // A jump out of the finally block only leaves the try statements around it
+            self.current_mut().tries.truncate(index);
+
+            for _ in 0..try_block.handlers {
+                self.emit(OpCode::PopHandler);
+            }
+
+            if let Some(finally) = &try_block.finally {
+                let hidden: Vec<String> = self.current_mut().locals[try_block.locals..].iter_mut()
+                                                                                       .map(|local| std::mem::take(&mut local.name))
+                                                                                       .collect();
+                self.begin_scope();
+                let result: Result<(), LoxError> = self.statements(finally);
+                self.end_scope();
+
+                for (local, name) in self.current_mut().locals[try_block.locals..].iter_mut().zip(hidden) {
+                    local.name = name;
+                }
+
+                result?;
+            }
+        }
+
+        self.current_mut().tries = tries;
+        Ok(())
+    }
+
+    /// Compiles a class. The method closures are left on the stack above the superclass, followed by the getters and
+    /// setters, and the class is built from them at runtime. Static members are added to the class once it is defined.
+    fn class(&mut self, name: &Token, superclass: &Option<Expr>, methods: &[Statement], accessors: &[Statement], statics: &[Statement], doc: &Option<String>) -> Result<(), LoxError> {
+        let is_global: bool = self.is_global_scope();
+
+        // A local class gets its slot first, so its methods can capture it
+        if !is_global {
+            self.emit(OpCode::Nil);
+            self.add_local(name.lexeme.clone());
+        }
+
+        if let Some(superclass) = superclass {
+            self.expression(superclass)?;
+            self.begin_scope();
+            self.add_local(String::from("super"));
+        }
+
+        for method in methods {
+            if let Statement::FunDeclaration(method_name, params, body, doc) = method {
+                let kind: FunctionKind = match method_name.lexeme.as_str() {
+                    "init" => FunctionKind::Initializer,
+                    _ => FunctionKind::Method
+                };
+
+                self.function(method_name, params, body, doc, kind)?;
+            }
+        }
+
+        let (getters, setters): (Vec<&Statement>, Vec<&Statement>) = accessors.iter().partition(|accessor| {
+            matches!(accessor, Statement::FunDeclaration(_, params, _, _) if params.is_empty())
+        });
+
+        for accessor in getters.iter().chain(setters.iter()) {
+            if let Statement::FunDeclaration(accessor_name, params, body, doc) = accessor {
+                self.function(accessor_name, params, body, doc, FunctionKind::Method)?;
+            }
+        }
+
+        let class: ClassProto = ClassProto {
+            name: name.lexeme.clone(),
+            methods: methods.len(),
+            getters: getters.len(),
+            setters: setters.len(),
+            inherits: superclass.is_some(),
+            doc: doc.clone()
+        };
+        let index: u32 = self.chunk().add_class(class);
+        self.emit(OpCode::Class(index));
+
+        // The scope declaring super is still open here, so globals are defined directly
+        if is_global {
+            let name: u32 = self.name_constant(&name.lexeme);
+            self.emit(OpCode::DefineGlobal(name));
+        }
+
+        else {
+            self.set_variable(&name.lexeme);
+            self.emit(OpCode::Pop);
+        }
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        if statics.is_empty() {
+            return Ok(());
+        }
+
+        self.get_variable(&name.lexeme);
+
+        for member in statics {
+            match member {
+                Statement::FunDeclaration(method_name, params, body, doc) => {
+                    self.function(method_name, params, body, doc, FunctionKind::Function)?;
+                    let name: u32 = self.name_constant(&method_name.lexeme);
+                    self.emit(OpCode::DefineStatic(name));
+                },
+                Statement::Var(field_name, initializer) => {
+                    match initializer {
+                        Some(expr) => self.expression(expr)?,
+                        None => { self.emit(OpCode::Nil); }
+                    }
+
+                    let name: u32 = self.name_constant(&field_name.lexeme);
+                    self.emit(OpCode::DefineStatic(name));
+                },
+                _ => ()
+            }
+        }
+
+        self.emit(OpCode::Pop);
+        Ok(())
+    }
+
+    /// Compiles a function and emits the instruction which creates its closure.
+    fn function(&mut self, name: &Token, params: &[Token], body: &[Statement], doc: &Option<String>, kind: FunctionKind) -> Result<(), LoxError> {
+        self.begin_function(name.lexeme.clone(), params.len(), kind);
+        self.begin_scope();
+
+        for param in params {
+            self.add_local(param.lexeme.clone());
+        }
+
+        self.statements(body)?;
+        self.emit_return();
+
+        let mut function: Function = self.end_function();
+        function.doc = doc.clone();
+        let index: u32 = self.chunk().add_function(function);
+        self.emit(OpCode::Closure(index));
+        Ok(())
+    }
+
+    /// Compiles each type of expression
+    fn expression(&mut self, expr: &Expr) -> Result<(), LoxError> {
+        match expr {
+            Expr::Literal(value) => {
+                match value {
+                    Value::Nil() => self.emit(OpCode::Nil),
+                    Value::Bool(true) => self.emit(OpCode::True),
+                    Value::Bool(false) => self.emit(OpCode::False),
+                    _ => {
+                        let index: u32 = self.chunk().add_constant(value.clone());
+                        self.emit(OpCode::Constant(index))
+                    }
+                };
+            },
+            Expr::Unary(op, right) => {
+                self.expression(right)?;
+                self.emit(OpCode::Unary(op.token_type));
+            },
+            Expr::Binary(left, op, right) => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.emit(OpCode::Binary(op.token_type));
+            },
+            Expr::Grouping(expr) => self.expression(expr)?,
+            Expr::Logical(left, op, right) => {
+                if op.token_type != TokenType::And && op.token_type != TokenType::Or {
+                    return Err(LoxError::CompilerBug(Statement::Expression(expr.clone()), String::from("Invalid operator used in Expr::Logical")));
+                }
+
+                // Both sides are always evaluated, as in the tree-walking interpreter
+                self.expression(left)?;
+                self.expression(right)?;
+                self.emit(OpCode::Logical(op.token_type));
+            },
+            Expr::Variable(name, _) | Expr::This(name, _) => self.get_variable(&name.lexeme),
+            Expr::Assign(name, value, _) => {
+                self.expression(value)?;
+                self.set_variable(&name.lexeme);
+            },
+            Expr::Call(callee, args) => {
+                self.expression(callee)?;
+
+                for arg in args.iter() {
+                    self.expression(arg)?;
+                }
+
+                self.emit(OpCode::Call(args.len() as u32));
+            },
+            Expr::Get(object, property) => {
+                self.expression(object)?;
+                let name: u32 = self.name_constant(&property.lexeme);
+                self.emit(OpCode::GetProperty(name));
+            },
+            Expr::Set(object, property, value) => {
+                self.expression(object)?;
+                self.expression(value)?;
+                let name: u32 = self.name_constant(&property.lexeme);
+                self.emit(OpCode::SetProperty(name));
+            },
+            // The target's parts are duplicated, so they are still on the stack to be set
+            Expr::UpdateProperty(object, property, op, value) => {
+                self.expression(object)?;
+                self.emit(OpCode::Duplicate(1));
+                let name: u32 = self.name_constant(&property.lexeme);
+                self.emit(OpCode::GetProperty(name));
+                self.expression(value)?;
+                self.emit(OpCode::Binary(op.token_type));
+                self.emit(OpCode::SetProperty(name));
+            },
+            Expr::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element)?;
+                }
+
+                self.emit(OpCode::List(elements.len() as u32));
+            },
+            Expr::Lambda(name, params, body) => self.function(name, params, body, &None, FunctionKind::Function)?,
+            Expr::Map(entries) => {
+                for (key, value) in entries.iter() {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+
+                self.emit(OpCode::Map(entries.len() as u32));
+            },
+            Expr::Stringify(expr) => {
+                self.expression(expr)?;
+                self.emit(OpCode::Stringify);
+            },
+            Expr::Index(list, index) => {
+                self.expression(list)?;
+                self.expression(index)?;
+                self.emit(OpCode::GetIndex);
+            },
+            Expr::SetIndex(list, index, value) => {
+                self.expression(list)?;
+                self.expression(index)?;
+                self.expression(value)?;
+                self.emit(OpCode::SetIndex);
+            },
+            Expr::UpdateIndex(collection, index, op, value) => {
+                self.expression(collection)?;
+                self.expression(index)?;
+                self.emit(OpCode::Duplicate(2));
+                self.emit(OpCode::GetIndex);
+                self.expression(value)?;
+                self.emit(OpCode::Binary(op.token_type));
+                self.emit(OpCode::SetIndex);
+            },
+            Expr::Super(_, method, _) => {
+                self.get_variable("this");
+                self.get_variable("super");
+                let name: u32 = self.name_constant(&method.lexeme);
+                self.emit(OpCode::GetSuper(name));
+            },
+            Expr::Yield(_, value) => {
+                self.expression(value)?;
+                self.emit(OpCode::Yield);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Emits the instruction which reads a variable.
+    fn get_variable(&mut self, name: &str) {
+        let state: usize = self.functions.len() - 1;
+
+        let op: OpCode = if let Some(slot) = self.resolve_local(state, name) {
+            OpCode::GetLocal(slot)
+        }
+
+        else if let Some(upvalue) = self.resolve_upvalue(state, name) {
+            OpCode::GetUpvalue(upvalue)
+        }
+
+        else {
+            OpCode::GetGlobal(self.name_constant(name))
+        };
+
+        self.emit(op);
+    }
+
+    /// Emits the instruction which assigns the value on top of the stack to a variable, replacing it with nil.
+    fn set_variable(&mut self, name: &str) {
+        let state: usize = self.functions.len() - 1;
+
+        let op: OpCode = if let Some(slot) = self.resolve_local(state, name) {
+            OpCode::SetLocal(slot)
+        }
+
+        else if let Some(upvalue) = self.resolve_upvalue(state, name) {
+            OpCode::SetUpvalue(upvalue)
+        }
+
+        else {
+            OpCode::SetGlobal(self.name_constant(name))
+        };
+
+        self.emit(op);
+    }
+
+    /// Binds the value on top of the stack to a new variable. At the top level this defines a global, otherwise the
+    /// value's stack slot becomes a local.
+    fn define_variable(&mut self, name: &str) {
+        if self.is_global_scope() {
+            let name: u32 = self.name_constant(name);
+            self.emit(OpCode::DefineGlobal(name));
+        }
+
+        else {
+            self.add_local(String::from(name));
+        }
+    }
+
+    /// Finds the stack slot of a local variable of a function being compiled.
+    fn resolve_local(&self, state: usize, name: &str) -> Option<u32> {
+        self.functions[state].locals.iter()
+                                    .rposition(|local| local.name == name)
+                                    .map(|slot| slot as u32)
+    }
+
+    /// Finds a variable captured from the functions enclosing a function being compiled, adding it to the upvalues of
+    /// every function it passes through.
+    fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<u32> {
+        if state == 0 {
+            return None;
+        }
+
+        if let Some(slot) = self.resolve_local(state - 1, name) {
+            self.functions[state - 1].locals[slot as usize].is_captured = true;
+            return Some(self.add_upvalue(state, UpvalueIndex { is_local: true, index: slot }));
+        }
+
+        let upvalue: u32 = self.resolve_upvalue(state - 1, name)?;
+        Some(self.add_upvalue(state, UpvalueIndex { is_local: false, index: upvalue }))
+    }
+
+    fn add_upvalue(&mut self, state: usize, upvalue: UpvalueIndex) -> u32 {
+        let upvalues: &mut Vec<UpvalueIndex> = &mut self.functions[state].function.upvalues;
+
+        match upvalues.iter().position(|existing| *existing == upvalue) {
+            Some(index) => index as u32,
+            None => {
+                upvalues.push(upvalue);
+                (upvalues.len() - 1) as u32
+            }
+        }
+    }
+
+    fn add_local(&mut self, name: String) {
+        let state: &mut FunctionState = self.current_mut();
+        let depth: usize = state.scope_depth;
+        state.locals.push(Local { name, depth, is_captured: false });
+    }
+
+    fn begin_scope(&mut self) {
+        self.current_mut().scope_depth += 1;
+    }
+
+    /// Ends a scope, discarding its locals and closing the ones which were captured.
+    fn end_scope(&mut self) {
+        let state: &mut FunctionState = self.current_mut();
+        state.scope_depth -= 1;
+        let mut ops: Vec<OpCode> = Vec::new();
+
+        while let Some(local) = state.locals.last() && local.depth > state.scope_depth {
+            ops.push(ternary!(local.is_captured, OpCode::CloseUpvalue, OpCode::Pop));
+            state.locals.pop();
+        }
+
+        for op in ops {
+            self.emit(op);
+        }
+    }
+
+    fn begin_function(&mut self, name: String, arity: usize, kind: FunctionKind) {
+        // The first slot of a call holds the callee, or the instance for methods
+        let receiver: &str = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            FunctionKind::Script | FunctionKind::Function => ""
+        };
+
+        let state = FunctionState {
+            function: Function::new(name, arity),
+            kind,
+            locals: vec![Local { name: String::from(receiver), depth: 0, is_captured: false }],
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new()
+        };
+
+        self.functions.push(state);
+    }
+
+    fn end_function(&mut self) -> Function {
+        self.functions.pop().expect("No function is being compiled.").function
+    }
+
+    /// Emits the implicit return at the end of a function.
+    fn emit_return(&mut self) {
+        if self.current().kind == FunctionKind::Initializer {
+            self.emit(OpCode::GetLocal(0));
+        }
+
+        else {
+            self.emit(OpCode::Nil);
+        }
+
+        self.emit(OpCode::Return);
+    }
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.chunk().write(op)
+    }
+
+    /// Points a jump at the next instruction.
+    fn patch_jump(&mut self, jump: usize) {
+        let target: usize = self.chunk().len();
+        self.chunk().patch_jump(jump, target);
+    }
+
+    fn name_constant(&mut self, name: &str) -> u32 {
+        self.chunk().add_constant(Value::Str(String::from(name)))
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.current().scope_depth == 0
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.current_mut().function.chunk
+    }
+
+    fn current(&self) -> &FunctionState {
+        self.functions.last().expect("No function is being compiled.")
+    }
+
+    fn current_mut(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().expect("No function is being compiled.")
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,80 @@
+use std::{cell::RefCell, fmt::{self, Debug}, rc::Rc};
+use crate::interpreter::{environment::Environment, gc::{Trace, address}};
+use crate::types::values::Value;
+use crate::vm::chunk::Chunk;
+
+/// A function compiled to bytecode.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub upvalues: Vec<UpvalueIndex>,
+    pub doc: Option<String>,
+    // Whether calling the function returns a generator, rather than running its body
+    pub is_generator: bool
+}
+
+/// Where a closure captures an upvalue from when it is created: a stack slot of the enclosing call, or one of the
+/// enclosing closure's own upvalues.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UpvalueIndex {
+    pub is_local: bool,
+    pub index: u32
+}
+
+impl Function {
+    pub fn new(name: String, arity: usize) -> Self {
+        Self { name, arity, chunk: Chunk::new(), upvalues: Vec::new(), doc: None, is_generator: false }
+    }
+}
+
+/// A function together with the variables it has captured, and the globals of the module it was declared in.
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    pub env: Environment
+}
+
+/// A captured variable. It points into the stack while the variable is in scope, and holds the value itself once the
+/// variable's scope has ended.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value)
+}
+
+impl Closure {
+    pub fn new(function: Rc<Function>, upvalues: Vec<Rc<RefCell<Upvalue>>>, env: Environment) -> Self {
+        Self { function, upvalues, env }
+    }
+}
+
+// Captured values can refer back to the closure, so they are left out.
+impl Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.function.name)
+    }
+}
+
+// Closures are compared by identity.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// The environment only holds globals, which are always reachable.
+impl Trace for Closure {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        self.upvalues.iter().for_each(|upvalue| edges.push(address(upvalue)));
+    }
+}
+
+impl Trace for Upvalue {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        if let Upvalue::Closed(value) = self {
+            value.trace(edges);
+        }
+    }
+}
@@ -0,0 +1,764 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::LoxError;
+use crate::interpreter::{context::NativeContext, environment::Environment, gc::{Heap, Trace, address}, modules, operators};
+use crate::interpreter::interpret::{DEFAULT_MAX_CALL_DEPTH, catch_error, grow_stack, check_arity, check_class_arity, find_binary_method, find_getter, find_method, iteration_method, find_setter, get_index, get_property, set_index, set_property};
+use crate::types::{token::Token, token_type::TokenType, values::{Value, callable::LoxCallable, class::LoxClass, generator::{GeneratorState, LoxGenerator}, iterator::LoxIterator, list::LoxList, map::LoxMap, object::LoxObject}};
+use crate::vm::{chunk::{ClassProto, OpCode}, compiler::Compiler, function::{Closure, Function, Upvalue, UpvalueIndex}};
+
+/// A stack based virtual machine which runs compiled Lox programs.
+///
+/// The VM shares its values, globals and output sink with the tree-walking interpreter, so a program behaves the same
+/// on either of them.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    // Upvalues which still point into the stack, ordered by stack slot
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    env: Environment,
+    output: Rc<RefCell<dyn Write>>,
+    handlers: Vec<Handler>,
+    // Errors which are raised again when the finally blocks they entered finish
+    pending: Vec<PendingError>,
+    next_pending: i64,
+    // Calls to native functions in progress, which do not have call frames
+    native_calls: usize,
+    // Frames which run whole programs, rather than calls
+    script_frames: usize,
+    max_call_depth: usize
+}
+
+/// A call in progress. The callee, or the instance for methods, is in the first of its stack slots.
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    // The generator whose body the frame runs, which suspends when it yields
+    generator: Option<Rc<RefCell<LoxGenerator>>>
+}
+
+/// The call frame of a generator between resumes. Stack slots, handlers and pending errors are kept relative to the
+/// base of the frame, as the generator can be resumed with a different stack below it.
+pub(crate) struct Suspended {
+    closure: Rc<Closure>,
+    ip: usize,
+    stack: Vec<Value>,
+    // Upvalues which pointed into the frame, with the slots they are reopened at
+    upvalues: Vec<(Rc<RefCell<Upvalue>>, usize)>,
+    handlers: Vec<Handler>,
+    pending: Vec<PendingError>
+}
+
+// Errors kept for finally blocks are left out, which keeps whatever they refer to alive.
+impl Trace for Suspended {
+    fn trace(&self, edges: &mut Vec<usize>) {
+        edges.push(address(&self.closure));
+        self.stack.iter().for_each(|value| value.trace(edges));
+        self.upvalues.iter().for_each(|(upvalue, _)| edges.push(address(upvalue)));
+    }
+}
+
+/// Where execution continues when an error is raised inside a try statement.
+struct Handler {
+    kind: HandlerKind,
+    // The number of call frames and stack slots when the handler was set up
+    frames: usize,
+    stack_size: usize,
+    target: usize
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum HandlerKind {
+    Catch,
+    Finally
+}
+
+/// An error interrupted by a finally block. The block is entered with the error's id in its first slot.
+struct PendingError {
+    id: i64,
+    slot: usize,
+    error: LoxError
+}
+
+impl Vm {
+    pub fn new(env: Environment, output: Rc<RefCell<dyn Write>>) -> Self {
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            open_upvalues: Vec::new(),
+            env,
+            output,
+            handlers: Vec::new(),
+            pending: Vec::new(),
+            next_pending: 0,
+            native_calls: 0,
+            script_frames: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH
+        }
+    }
+
+    /// Sets the maximum depth of nested calls. Going deeper raises a stack overflow error.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Runs a compiled program, returning the value it returns.
+    pub fn interpret(&mut self, function: Function) -> Result<Value, LoxError> {
+        let script: Rc<Closure> = self.env.heap().closure(Closure::new(Rc::new(function), Vec::new(), self.env.clone()));
+
+        // The program is not a call, so its frame does not count towards the call depth
+        self.script_frames += 1;
+        let result: Result<Value, LoxError> = self.call_value(Value::Callable(LoxCallable::Compiled(script, None)), Vec::new());
+        self.script_frames -= 1;
+
+        result
+    }
+
+    /// Calls a callable or constructs a class with already evaluated arguments, running it to completion.
+    ///
+    /// If the call fails, the stack is unwound back to where it was, so the VM can keep being used.
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, LoxError> {
+        let frame_count: usize = self.frames.len();
+        let stack_size: usize = self.stack.len();
+        let arg_count: usize = args.len();
+
+        self.stack.push(callee);
+        self.stack.extend(args);
+
+        let result: Result<Value, LoxError> = match self.call(arg_count) {
+            Ok(()) if self.frames.len() > frame_count => grow_stack(|| self.run(frame_count)),
+            Ok(()) => Ok(self.pop()),
+            Err(err) => Err(err)
+        };
+
+        if result.is_err() {
+            self.unwind(frame_count, stack_size);
+            self.handlers.retain(|handler| handler.frames <= frame_count);
+        }
+
+        result
+    }
+
+    /// Resumes a generator until its body yields a value or returns. The yield it stopped at evaluates to the value it
+    /// is resumed with. Resuming a finished generator returns nil.
+    pub(crate) fn resume(&mut self, generator: &Rc<RefCell<LoxGenerator>>, sent: Value) -> Result<Value, LoxError> {
+        let call_depth: usize = (self.frames.len() + self.native_calls).saturating_sub(self.script_frames);
+
+        if call_depth >= self.max_call_depth {
+            return Err(LoxError::StackOverflow(String::from("generator")));
+        }
+
+        let Some(state) = LoxGenerator::start(generator)? else { return Ok(Value::Nil()) };
+        let GeneratorState::Compiled(suspended) = state else {
+            return Err(LoxError::ValueError(Value::Generator(generator.clone()), String::from("Generator can only be resumed by the tree-walking interpreter.")));
+        };
+
+        let frame_count: usize = self.frames.len();
+        let base: usize = self.stack.len();
+        self.stack.extend(suspended.stack);
+
+        // A generator which has not started yet has no yield to give the value to
+        if suspended.ip > 0 {
+            self.stack.push(sent);
+        }
+
+        for (upvalue, slot) in suspended.upvalues {
+            let value: Value = match std::mem::replace(&mut *upvalue.borrow_mut(), Upvalue::Open(base + slot)) {
+                Upvalue::Closed(value) => value,
+                Upvalue::Open(_) => Value::Nil()
+            };
+
+            self.stack[base + slot] = value;
+            self.open_upvalues.push(upvalue);
+        }
+
+        self.frames.push(CallFrame { closure: suspended.closure, ip: suspended.ip, base, generator: Some(generator.clone()) });
+        self.handlers.extend(suspended.handlers.into_iter().map(|handler| {
+            Handler { frames: handler.frames + frame_count, stack_size: handler.stack_size + base, ..handler }
+        }));
+        self.pending.extend(suspended.pending.into_iter().map(|pending| PendingError { slot: pending.slot + base, ..pending }));
+
+        let result: Result<Value, LoxError> = grow_stack(|| self.run(frame_count));
+
+        if result.is_err() {
+            self.unwind(frame_count, base);
+            self.handlers.retain(|handler| handler.frames <= frame_count);
+        }
+
+        generator.borrow_mut().finish();
+        result
+    }
+
+    /// Retrieves a global binding.
+    pub fn get_global(&self, name: &str) -> Result<Value, LoxError> {
+        self.env.get_global(name)
+    }
+
+    /// Returns the heap which tracks the objects of the running program.
+    pub fn heap(&self) -> &Heap {
+        self.env.heap()
+    }
+
+    /// Runs instructions until the frame the run started in returns, returning its result.
+    fn run(&mut self, frame_count: usize) -> Result<Value, LoxError> {
+        loop {
+            match self.step(frame_count) {
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => (),
+                Err(error) => self.handle_error(error, frame_count)?
+            }
+        }
+    }
+
+    /// Runs a single instruction, returning the result of the run once the frame it started in returns.
+    fn step(&mut self, frame_count: usize) -> Result<Option<Value>, LoxError> {
+        let frame: &mut CallFrame = self.frames.last_mut().expect("The VM ran without a call frame.");
+        let op: OpCode = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+
+        match op {
+            OpCode::Constant(index) => {
+                let value: Value = self.frame().closure.function.chunk.constants[index as usize].clone();
+                self.stack.push(value);
+            },
+            OpCode::Nil => self.stack.push(Value::Nil()),
+            OpCode::True => self.stack.push(Value::Bool(true)),
+            OpCode::False => self.stack.push(Value::Bool(false)),
+            OpCode::Pop => { self.pop(); },
+            OpCode::Duplicate(count) => {
+                let start: usize = self.stack.len() - count as usize;
+                self.stack.extend_from_within(start..);
+            },
+            OpCode::GetLocal(slot) => {
+                let value: Value = self.stack[self.frame().base + slot as usize].clone();
+                self.stack.push(value);
+            },
+            OpCode::SetLocal(slot) => {
+                let slot: usize = self.frame().base + slot as usize;
+                self.stack[slot] = self.pop();
+                self.stack.push(Value::Nil());
+            },
+            // Globals belong to the module the running closure was declared in
+            OpCode::GetGlobal(name) => {
+                let value: Value = self.frame().closure.env.get_at(None, &self.name_token(name))?;
+                self.stack.push(value);
+            },
+            OpCode::DefineGlobal(name) => {
+                let value: Value = self.pop();
+                let mut env: Environment = self.frame().closure.env.clone();
+                env.define_global(self.name(name), value);
+            },
+            OpCode::SetGlobal(name) => {
+                let value: Value = self.pop();
+                let mut env: Environment = self.frame().closure.env.clone();
+                env.assign_at(None, self.name_token(name), value)?;
+                self.stack.push(Value::Nil());
+            },
+            OpCode::GetUpvalue(index) => {
+                let value: Value = match &*self.frame().closure.upvalues[index as usize].borrow() {
+                    Upvalue::Open(slot) => self.stack[*slot].clone(),
+                    Upvalue::Closed(value) => value.clone()
+                };
+
+                self.stack.push(value);
+            },
+            OpCode::SetUpvalue(index) => {
+                let value: Value = self.pop();
+                let upvalue: Rc<RefCell<Upvalue>> = self.frame().closure.upvalues[index as usize].clone();
+
+                match &mut *upvalue.borrow_mut() {
+                    Upvalue::Open(slot) => self.stack[*slot] = value,
+                    Upvalue::Closed(closed) => *closed = value
+                }
+
+                self.stack.push(Value::Nil());
+            },
+            OpCode::GetProperty(name) => {
+                let object: Value = self.pop();
+                let name: Token = self.name_token(name);
+
+                let value: Value = match find_getter(&object, &name.lexeme) {
+                    Some(getter) => self.call_value(Value::Callable(getter), Vec::new())?,
+                    None => get_property(object, &name)?
+                };
+
+                self.stack.push(value);
+            },
+            OpCode::SetProperty(name) => {
+                let value: Value = self.pop();
+                let object: Value = self.pop();
+                let name: Token = self.name_token(name);
+
+                match find_setter(&object, &name.lexeme) {
+                    Some(setter) => { self.call_value(Value::Callable(setter), vec![value])?; },
+                    None => set_property(object, name, value)?
+                }
+
+                self.stack.push(Value::Nil());
+            },
+            OpCode::GetSuper(name) => {
+                let superclass: Value = self.pop();
+                let this: Value = self.pop();
+                let method: LoxCallable = self.super_method(superclass, this, name)?;
+                self.stack.push(Value::Callable(method));
+            },
+            OpCode::List(count) => {
+                let items: Vec<Value> = self.stack.split_off(self.stack.len() - count as usize);
+                let list: Value = Value::List(self.env.heap().list(LoxList::new(items)));
+                self.stack.push(list);
+            },
+            OpCode::Map(count) => {
+                let entries: Vec<Value> = self.stack.split_off(self.stack.len() - 2 * count as usize);
+                let mut map: LoxMap = LoxMap::new();
+
+                for entry in entries.chunks_exact(2) {
+                    map.set(entry[0].clone(), entry[1].clone())?;
+                }
+
+                self.stack.push(Value::Map(self.env.heap().map(map)));
+            },
+            OpCode::GetIndex => {
+                let index: Value = self.pop();
+                let list: Value = self.pop();
+                self.stack.push(get_index(list, index)?);
+            },
+            OpCode::SetIndex => {
+                let value: Value = self.pop();
+                let index: Value = self.pop();
+                let list: Value = self.pop();
+                set_index(list, index, value)?;
+                self.stack.push(Value::Nil());
+            },
+            OpCode::Unary(op) => {
+                let value: Value = self.pop();
+
+                let result: Value = match operators::unary_method(op).and_then(|name| find_method(&value, name)) {
+                    Some(method) => self.call_value(Value::Callable(method), Vec::new())?,
+                    None => operators::unary(op, value)?
+                };
+
+                self.stack.push(result);
+            },
+            OpCode::Binary(op) => {
+                let right: Value = self.pop();
+                let left: Value = self.pop();
+
+                let result: Value = match find_binary_method(&left, op, &right) {
+                    Some((method, other)) => operators::overloaded_binary(op, self.call_value(Value::Callable(method), vec![other])?)?,
+                    None => operators::binary(left, op, right)?
+                };
+
+                self.stack.push(result);
+            },
+            OpCode::Logical(op) => {
+                let right: Value = self.pop();
+                let left: Value = self.pop();
+                self.stack.push(Value::Bool(operators::logical(left, op, right)?));
+            },
+            OpCode::Stringify => {
+                let value: Value = self.pop();
+                let string: String = self.stringify(&value)?;
+                self.stack.push(Value::Str(string));
+            },
+            OpCode::Print => {
+                let value: Value = self.pop();
+                let string: String = self.stringify(&value)?;
+
+                if let Err(err) = writeln!(self.output.borrow_mut(), "{string}") {
+                    return Err(LoxError::ValueError(value, format!("Value could not be printed: {err}")));
+                }
+            },
+            OpCode::Jump(target) => self.frame_mut().ip = target as usize,
+            OpCode::Iterate => {
+                let iterable: Value = self.pop();
+
+                let iterator: Value = match iterable {
+                    Value::Instance(_) | Value::Host(_) => {
+                        let method: LoxCallable = iteration_method(&iterable, "iterator")?;
+                        self.call_value(Value::Callable(method), Vec::new())?
+                    },
+                    _ => iterable
+                };
+
+                let iterator: Value = match iterator {
+                    Value::Instance(_) | Value::Host(_) => iterator,
+                    _ => LoxIterator::over(iterator, self.env.heap())?
+                };
+
+                self.stack.push(iterator);
+            },
+            OpCode::ForNext(target) => {
+                let iterator: Value = self.stack.last().expect("The VM stack is empty.").clone();
+
+                // Iterator objects are done once next() returns nil
+                let item: Option<Value> = match iterator {
+                    Value::Iterator(iterator) => iterator.borrow_mut().next(),
+                    Value::Generator(generator) => {
+                        let item: Value = self.resume(&generator, Value::Nil())?;
+
+                        match generator.borrow().is_done() {
+                            true => None,
+                            false => Some(item)
+                        }
+                    },
+                    _ => match self.call_value(Value::Callable(iteration_method(&iterator, "next")?), Vec::new())? {
+                        Value::Nil() => None,
+                        item => Some(item)
+                    }
+                };
+
+                match item {
+                    Some(item) => self.stack.push(item),
+                    None => self.frame_mut().ip = target as usize
+                }
+            },
+            OpCode::JumpIfFalse(target) => {
+                let condition: Value = self.stack.last().expect("The VM stack is empty.").clone();
+
+                if !operators::get_value_truth(condition)? {
+                    self.frame_mut().ip = target as usize;
+                }
+            },
+            OpCode::Call(arg_count) => self.call(arg_count as usize)?,
+            OpCode::Closure(index) => {
+                let function: Rc<Function> = self.frame().closure.function.chunk.functions[index as usize].clone();
+                let upvalues: Vec<Rc<RefCell<Upvalue>>> = function.upvalues.iter()
+                                                                            .map(|upvalue| self.capture(*upvalue))
+                                                                            .collect();
+                let env: Environment = self.frame().closure.env.clone();
+                let closure: Rc<Closure> = self.env.heap().closure(Closure::new(function, upvalues, env));
+                self.stack.push(Value::Callable(LoxCallable::Compiled(closure, None)));
+            },
+            OpCode::CloseUpvalue => {
+                self.close_upvalues(self.stack.len() - 1);
+                self.pop();
+            },
+            OpCode::Return => {
+                let result: Value = self.pop();
+                let frame: CallFrame = self.frames.pop().expect("The VM returned without a call frame.");
+                self.close_upvalues(frame.base);
+                self.stack.truncate(frame.base);
+                self.pending.retain(|pending| pending.slot < frame.base);
+
+                if self.frames.len() == frame_count {
+                    return Ok(Some(result));
+                }
+
+                self.stack.push(result);
+            },
+            OpCode::Yield => {
+                let value: Value = self.pop();
+                let frame: CallFrame = self.frames.pop().expect("The VM yielded without a call frame.");
+                let generator: Rc<RefCell<LoxGenerator>> = frame.generator.clone().expect("The VM yielded outside of a generator.");
+                let suspended: Suspended = self.suspend(frame);
+                generator.borrow_mut().suspend(GeneratorState::Compiled(suspended));
+                return Ok(Some(value));
+            },
+            OpCode::DefineStatic(name) => {
+                let value: Value = self.pop();
+
+                if let Some(Value::Class(class)) = self.stack.last() {
+                    class.define_static(self.name(name), value);
+                }
+            },
+            OpCode::Import(path) => {
+                let module: Value = self.import(&self.name(path))?;
+                self.stack.push(module);
+            },
+            OpCode::PushCatch(target) => self.push_handler(HandlerKind::Catch, target),
+            OpCode::PushFinally(target) => self.push_handler(HandlerKind::Finally, target),
+            OpCode::PopHandler => { self.handlers.pop(); },
+            OpCode::Throw => {
+                let value: Value = self.pop();
+                return Err(LoxError::Thrown(value));
+            },
+            OpCode::EndFinally => {
+                if let Some(Value::Int(id)) = self.stack.last().cloned()
+                   && let Some(position) = self.pending.iter().position(|pending| pending.id == id) {
+                    return Err(self.pending.remove(position).error);
+                }
+            },
+            OpCode::Class(index) => {
+                let class: LoxClass = self.build_class(index)?;
+                let class: Rc<LoxClass> = self.env.heap().class(class);
+                self.stack.push(Value::Class(class));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn push_handler(&mut self, kind: HandlerKind, target: u32) {
+        let handler = Handler { kind, frames: self.frames.len(), stack_size: self.stack.len(), target: target as usize };
+        self.handlers.push(handler);
+    }
+
+    /// Jumps to the innermost handler which can handle an error, out of those set up since the run started. Catch
+    /// clauses get the error as a value, while finally blocks raise it again when they finish. Errors which no
+    /// handler takes are returned.
+    fn handle_error(&mut self, mut error: LoxError, frame_count: usize) -> Result<(), LoxError> {
+        while let Some(handler) = self.handlers.last() && handler.frames > frame_count {
+            let handler: Handler = self.handlers.pop().expect("The VM has no handlers.");
+
+            match handler.kind {
+                HandlerKind::Catch => match catch_error(error, &self.env) {
+                    Ok(value) => {
+                        self.unwind(handler.frames, handler.stack_size);
+                        self.stack.push(value);
+                    },
+                    Err(uncaught) => {
+                        error = uncaught;
+                        continue;
+                    }
+                },
+                HandlerKind::Finally => {
+                    self.unwind(handler.frames, handler.stack_size);
+                    self.next_pending += 1;
+                    self.pending.push(PendingError { id: self.next_pending, slot: handler.stack_size, error });
+                    self.stack.push(Value::Int(self.next_pending));
+                }
+            }
+
+            self.frame_mut().ip = handler.target;
+            return Ok(());
+        }
+
+        Err(error)
+    }
+
+    /// Discards the call frames and stack slots above a point, closing the upvalues which point into them.
+    fn unwind(&mut self, frames: usize, stack_size: usize) {
+        self.close_upvalues(stack_size);
+        self.frames.truncate(frames);
+        self.stack.truncate(stack_size);
+        self.pending.retain(|pending| pending.slot < stack_size);
+    }
+
+    /// Calls the value below the arguments on top of the stack. Compiled functions get a new call frame, while
+    /// native functions and classes without initializers leave their result in place of the callee.
+    fn call(&mut self, arg_count: usize) -> Result<(), LoxError> {
+        let callee_slot: usize = self.stack.len() - arg_count - 1;
+        let callee: Value = self.stack[callee_slot].clone();
+
+        match callee {
+            Value::Callable(callable) => {
+                check_arity(&callable, arg_count, "function")?;
+                self.call_callable(callable, callee_slot)
+            },
+            Value::Class(class) => {
+                match class.find_method(&String::from("init")) {
+                    Some(init) => {
+                        check_arity(&init, arg_count, "constructor")?;
+                        let obj: Rc<RefCell<LoxObject>> = self.env.heap().instance(LoxObject::new(class));
+                        self.call_callable(init.bind(obj), callee_slot)
+                    },
+                    None => {
+                        check_class_arity(&class, arg_count)?;
+                        self.stack.truncate(callee_slot);
+                        let obj: Rc<RefCell<LoxObject>> = self.env.heap().instance(LoxObject::new(class));
+                        self.stack.push(Value::Instance(obj));
+                        Ok(())
+                    }
+                }
+            },
+            _ => Err(LoxError::ValueError(callee, String::from("Value is not callable!")))
+        }
+    }
+
+    fn call_callable(&mut self, callable: LoxCallable, callee_slot: usize) -> Result<(), LoxError> {
+        let call_depth: usize = (self.frames.len() + self.native_calls).saturating_sub(self.script_frames);
+
+        if call_depth >= self.max_call_depth {
+            return Err(LoxError::StackOverflow(callable.get_name().lexeme));
+        }
+
+        match callable {
+            LoxCallable::Native(_, func, _) => {
+                let args: Vec<Value> = self.stack.split_off(callee_slot + 1);
+                self.stack.pop();
+
+                self.native_calls += 1;
+                let result: Result<Value, LoxError> = func.call(&mut NativeContext::from_vm(self), args);
+                self.native_calls -= 1;
+
+                self.stack.push(result?);
+                Ok(())
+            },
+            LoxCallable::Compiled(closure, receiver) => {
+                if let Some(instance) = receiver {
+                    self.stack[callee_slot] = Value::Instance(instance);
+                }
+
+                // Calling a generator function only sets up its frame, which runs once the generator is resumed
+                if closure.function.is_generator {
+                    let stack: Vec<Value> = self.stack.split_off(callee_slot);
+                    let suspended = Suspended { closure, ip: 0, stack, upvalues: Vec::new(), handlers: Vec::new(), pending: Vec::new() };
+                    self.stack.push(Value::Generator(self.env.heap().generator(LoxGenerator::new(GeneratorState::Compiled(suspended)))));
+                    return Ok(());
+                }
+
+                self.frames.push(CallFrame { closure, ip: 0, base: callee_slot, generator: None });
+                Ok(())
+            },
+            callable @ LoxCallable::Closure(..) => {
+                Err(LoxError::ValueError(Value::Callable(callable), String::from("Function can only be called by the tree-walking interpreter.")))
+            }
+        }
+    }
+
+    /// Moves the stack slots, upvalues, handlers and pending errors of a generator's frame out of the VM, once the frame
+    /// has been popped.
+    fn suspend(&mut self, frame: CallFrame) -> Suspended {
+        let position: usize = self.open_upvalues.partition_point(|open| open_slot(open) < frame.base);
+        let upvalues: Vec<(Rc<RefCell<Upvalue>>, usize)> = self.open_upvalues.split_off(position).into_iter().map(|upvalue| {
+            let slot: usize = open_slot(&upvalue);
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].clone());
+            (upvalue, slot - frame.base)
+        }).collect();
+
+        let frame_count: usize = self.frames.len();
+        let position: usize = self.handlers.partition_point(|handler| handler.frames <= frame_count);
+        let handlers: Vec<Handler> = self.handlers.split_off(position).into_iter().map(|handler| {
+            Handler { frames: handler.frames - frame_count, stack_size: handler.stack_size - frame.base, ..handler }
+        }).collect();
+
+        let (pending, kept): (Vec<PendingError>, Vec<PendingError>) = std::mem::take(&mut self.pending).into_iter().partition(|pending| pending.slot >= frame.base);
+        self.pending = kept;
+        let pending: Vec<PendingError> = pending.into_iter().map(|pending| PendingError { slot: pending.slot - frame.base, ..pending }).collect();
+
+        let stack: Vec<Value> = self.stack.split_off(frame.base);
+        Suspended { closure: frame.closure, ip: frame.ip, stack, upvalues, handlers, pending }
+    }
+
+    /// Binds a method of a superclass to an instance, in the same way as a super expression in the tree-walking
+    /// interpreter.
+    fn super_method(&self, superclass: Value, this: Value, name: u32) -> Result<LoxCallable, LoxError> {
+        match superclass {
+            Value::Class(class) => {
+                match class.find_method(&self.name(name)) {
+                    Some(method) => {
+                        match this.into_instance() {
+                            Ok(this) => Ok(method.bind(this)),
+                            Err(value) => Err(LoxError::ValueError(value, String::from("this is a defined variable in this scope, instead of a keyword.")))
+                        }
+                    },
+                    None => Err(LoxError::NameError(String::from("super"), String::from("Name does not correspond to a function.")))
+                }
+            },
+            _ => Err(LoxError::ValueError(superclass, String::from("'super' does not refer to a class.")))
+        }
+    }
+
+    /// Builds a class from the method, getter and setter closures on top of the stack, and its superclass below them if
+    /// it has one.
+    fn build_class(&mut self, index: u32) -> Result<LoxClass, LoxError> {
+        let proto: &ClassProto = &self.frame().closure.function.chunk.classes[index as usize];
+        let (name, inherits, doc) = (proto.name.clone(), proto.inherits, proto.doc.clone());
+        let (method_count, getter_count, setter_count) = (proto.methods, proto.getters, proto.setters);
+
+        let setters: HashMap<String, LoxCallable> = self.pop_methods(setter_count);
+        let getters: HashMap<String, LoxCallable> = self.pop_methods(getter_count);
+        let methods: HashMap<String, LoxCallable> = self.pop_methods(method_count);
+
+        // The superclass stays on the stack, where it is the `super` variable of the methods
+        let superclass: Option<Rc<LoxClass>> = match inherits {
+            true => match self.stack.last().expect("The VM stack is empty.").clone().into_class() {
+                Ok(class) => Some(class),
+                Err(value) => return Err(LoxError::ValueError(value, String::from("A superclass must be a class.")))
+            },
+            false => None
+        };
+
+        Ok(LoxClass::new(name, superclass, methods, getters, setters, self.frame().closure.env.clone(), doc))
+    }
+
+    /// Converts a value to a string, calling the `__str__` method of an instance if it has one.
+    fn stringify(&mut self, value: &Value) -> Result<String, LoxError> {
+        match find_method(value, operators::STR_METHOD) {
+            Some(method) => operators::overloaded_str(self.call_value(Value::Callable(method), Vec::new())?),
+            None => Ok(value.to_string())
+        }
+    }
+
+    /// Pops a number of method closures off the stack, by their names.
+    fn pop_methods(&mut self, count: usize) -> HashMap<String, LoxCallable> {
+        self.stack.split_off(self.stack.len() - count)
+                  .into_iter()
+                  .filter_map(|method| method.into_callable().ok())
+                  .map(|method| (method.get_name().lexeme, method))
+                  .collect()
+    }
+
+    /// Imports a module, compiling and running it on a VM of its own the first time it is imported.
+    fn import(&self, path: &str) -> Result<Value, LoxError> {
+        modules::import(path, &self.frame().closure.env, |statements, module_env| {
+            let function: Function = Compiler::new().compile(&statements)?;
+            let mut vm = Vm::new(module_env.clone(), self.output.clone());
+            vm.set_max_call_depth(self.max_call_depth);
+            vm.interpret(function).map(|_| ())
+        })
+    }
+
+    /// Returns the upvalue a new closure captures, reusing an open upvalue if the stack slot has already been captured.
+    fn capture(&mut self, upvalue: UpvalueIndex) -> Rc<RefCell<Upvalue>> {
+        if !upvalue.is_local {
+            return self.frame().closure.upvalues[upvalue.index as usize].clone();
+        }
+
+        let slot: usize = self.frame().base + upvalue.index as usize;
+        let position: usize = self.open_upvalues.partition_point(|open| open_slot(open) < slot);
+
+        if let Some(open) = self.open_upvalues.get(position) && open_slot(open) == slot {
+            return open.clone();
+        }
+
+        let captured: Rc<RefCell<Upvalue>> = self.env.heap().upvalue(Upvalue::Open(slot));
+        self.open_upvalues.insert(position, captured.clone());
+        captured
+    }
+
+    /// Moves the values of every open upvalue at or above a stack slot out of the stack.
+    fn close_upvalues(&mut self, from: usize) {
+        let position: usize = self.open_upvalues.partition_point(|open| open_slot(open) < from);
+
+        for upvalue in self.open_upvalues.drain(position..) {
+            let slot: usize = open_slot(&upvalue);
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].clone());
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("The VM stack is empty.")
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("The VM ran without a call frame.")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("The VM ran without a call frame.")
+    }
+
+    /// Reads a name from the constants table of the current function.
+    fn name(&self, index: u32) -> String {
+        match &self.frame().closure.function.chunk.constants[index as usize] {
+            Value::Str(name) => name.clone(),
+            value => value.to_string()
+        }
+    }
+
+    fn name_token(&self, index: u32) -> Token {
+        Token::new(TokenType::Identifier, self.name(index), Value::None(), 0)
+    }
+}
+
+/// Returns the stack slot of an open upvalue.
+fn open_slot(upvalue: &Rc<RefCell<Upvalue>>) -> usize {
+    match &*upvalue.borrow() {
+        Upvalue::Open(slot) => *slot,
+        Upvalue::Closed(_) => usize::MAX
+    }
+}
@@ -0,0 +1,4 @@
+pub mod chunk;
+pub mod compiler;
+pub mod function;
+pub mod machine;
@@ -0,0 +1,57 @@
+use std::{fs, path::{Path, PathBuf}};
+use lox_rs::{Backend, Lox};
+use lox_rs::interpreter::output::OutputBuffer;
+
+/// The test files which end in an uncaught error, with their exit code and diagnostics. Every other file exits with 0
+/// and reports nothing.
+const FAILURES: [(&str, i32, &str); 6] = [
+    ("comments", 3, "[Value Error] Error for '1': Only functions and classes have doc comments.\n"),
+    ("compound_assignment", 8, "[Index Error] Error for '5': List index out of range.\n"),
+    ("for_in", 3, "[Value Error] Error for '42': Value is not iterable.\n"),
+    ("modules", 3, "[Value Error] Error for '<module shapes>': Module globals can't be set from outside the module.\n"),
+    ("operators", 3, "[Value Error] Error for '1': __str__ must return a string.\n"),
+    ("statics", 5, "[Name Error] Error for 'dimensions': Undefined property dimensions\n")
+];
+
+/// Runs a script file, returning its exit code, output and diagnostics.
+fn run_file(backend: Backend, path: &PathBuf) -> (i32, String, String) {
+    let source: String = fs::read_to_string(path).expect("Could not read the test file.");
+    let (output, diagnostics): (OutputBuffer, OutputBuffer) = (OutputBuffer::new(), OutputBuffer::new());
+    let mut lox = Lox::new();
+    lox.set_backend(backend);
+    lox.set_script_path(path);
+    lox.set_output(output.clone());
+    lox.set_diagnostics(diagnostics.clone());
+
+    let code: i32 = lox.run(&source);
+    (code, output.contents(), diagnostics.contents())
+}
+
+/// Returns the exit code, output and diagnostics a script file should give. The output is kept in `expected/`, in a
+/// file named after the script.
+fn expected(path: &Path) -> (i32, String, String) {
+    let name: &str = path.file_stem().and_then(|stem| stem.to_str()).expect("Test files have names.");
+    let output_path: PathBuf = path.with_file_name("expected").join(format!("{name}.out"));
+    let output: String = fs::read_to_string(&output_path).unwrap_or_else(|_| panic!("{} has no expected output.", path.display()));
+
+    match FAILURES.iter().find(|(failing, _, _)| *failing == name) {
+        Some((_, code, diagnostics)) => (*code, output, String::from(*diagnostics)),
+        None => (0, output, String::new())
+    }
+}
+
+#[test]
+fn test_files_give_the_expected_results_on_both_backends() {
+    let mut paths: Vec<PathBuf> = fs::read_dir("test-files").expect("Could not read the test files.")
+                                                           .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                                                           .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+                                                           .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let expected: (i32, String, String) = expected(&path);
+        assert_eq!(run_file(Backend::TreeWalker, &path), expected, "{} gives the wrong result.", path.display());
+        assert_eq!(run_file(Backend::Vm, &path), expected, "{} gives the wrong result on the VM.", path.display());
+    }
+}
@@ -0,0 +1,55 @@
+// LoxError carries whole AST nodes and values, as it does in the library.
+#![allow(clippy::result_large_err)]
+
+use lox_rs::{Backend, Lox, LoxError};
+use lox_rs::types::values::Value;
+
+const RECURSE: &str = "fun r(n) { if (n == 0) return 0; return 1 + r(n - 1); }";
+
+/// Evaluates a program after defining a recursive function. Tests run on threads with the default stack size, which is
+/// smaller than the stack of the main thread.
+fn eval_recursive(backend: Backend, max_call_depth: Option<usize>, source: &str) -> Result<Value, LoxError> {
+    let mut lox = Lox::new();
+    lox.set_backend(backend);
+
+    if let Some(max_call_depth) = max_call_depth {
+        lox.set_max_call_depth(max_call_depth);
+    }
+
+    lox.eval(RECURSE)?;
+    lox.eval(source)
+}
+
+#[test]
+fn runaway_recursion_is_a_stack_overflow() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let result: Result<Value, LoxError> = eval_recursive(backend, None, "r(100000);");
+        assert!(matches!(result, Err(LoxError::StackOverflow(ref name)) if name == "r"), "{backend:?}");
+    }
+}
+
+#[test]
+fn default_call_depth_is_reachable() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        assert_eq!(eval_recursive(backend, None, "r(900);").unwrap(), Value::Int(900), "{backend:?}");
+    }
+}
+
+#[test]
+fn raised_call_depth_is_reachable() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        assert_eq!(eval_recursive(backend, Some(3000), "r(2500);").unwrap(), Value::Int(2500), "{backend:?}");
+        assert!(matches!(eval_recursive(backend, Some(3000), "r(3500);"), Err(LoxError::StackOverflow(_))), "{backend:?}");
+    }
+}
+
+#[test]
+fn runaway_recursion_through_methods_is_a_stack_overflow() {
+    let source: &str = "class A { __add__(other) { return this + other; } } A() + 1;";
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+        assert!(matches!(lox.eval(source), Err(LoxError::StackOverflow(_))), "{backend:?}");
+    }
+}
@@ -0,0 +1,55 @@
+use lox_rs::{Backend, Lox, LoxError};
+use lox_rs::types::values::Value;
+
+#[test]
+fn eval_returns_trailing_expression() {
+    let mut lox = Lox::new();
+
+    assert_eq!(lox.eval("1 + 2;").unwrap(), Value::Int(3));
+    assert_eq!(lox.eval("var x = 1;").unwrap(), Value::Nil());
+}
+
+#[test]
+fn eval_keeps_globals_between_calls() {
+    let mut lox = Lox::new();
+    lox.eval("var greeting = \"hello\";").unwrap();
+
+    assert_eq!(lox.eval("greeting + \" world\";").unwrap(), Value::Str(String::from("hello world")));
+}
+
+#[test]
+fn eval_returns_first_syntax_error() {
+    let mut lox = Lox::new();
+
+    assert!(matches!(lox.eval("var = 1;"), Err(LoxError::ParseError(..))));
+}
+
+#[test]
+fn call_function_calls_global_function() {
+    let mut lox = Lox::new();
+    lox.eval("fun add(a, b) { return a + b; }").unwrap();
+
+    assert_eq!(lox.call_function("add", vec![Value::Int(2), Value::Int(3)]).unwrap(), Value::Int(5));
+    assert!(matches!(lox.call_function("add", vec![Value::Int(2)]), Err(LoxError::ArgumentError(..))));
+    assert!(matches!(lox.call_function("missing", Vec::new()), Err(LoxError::NameError(..))));
+}
+
+#[test]
+fn globals_can_be_read_and_written() {
+    let mut lox = Lox::new();
+    lox.set_global("limit", Value::Int(10));
+    lox.eval("var doubled = limit * 2;").unwrap();
+
+    assert_eq!(lox.get_global("doubled").unwrap(), Value::Int(20));
+    assert!(matches!(lox.get_global("missing"), Err(LoxError::NameError(..))));
+}
+
+#[test]
+fn switching_backends_keeps_values_but_not_functions() {
+    let mut lox = Lox::new();
+    lox.eval("var items = [1, 2]; fun first(list) { return list[0]; }").unwrap();
+    lox.set_backend(Backend::Vm);
+
+    assert_eq!(lox.eval("items[1];").unwrap(), Value::Int(2));
+    assert!(matches!(lox.eval("first(items);"), Err(LoxError::ValueError(..))));
+}
@@ -0,0 +1,112 @@
+// LoxError carries whole AST nodes and values, as it does in the library.
+#![allow(clippy::result_large_err)]
+
+use std::{cell::Cell, rc::Rc};
+use lox_rs::{Backend, Lox};
+use lox_rs::types::values::{Value, host::{HostObject, LoxHost}, iterator::LoxIterator};
+
+/// A host object which counts how many of its kind have been dropped.
+struct Tracker {
+    dropped: Rc<Cell<usize>>
+}
+
+impl HostObject for Tracker {
+    fn type_name(&self) -> String {
+        String::from("Tracker")
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.dropped.set(self.dropped.get() + 1);
+    }
+}
+
+/// Runs a script on each backend with a `tracker()` native, and an `iterate(value)` native which returns an iterator
+/// over a value, returning how many trackers were dropped after the script and a collection.
+fn dropped_trackers(source: &str) -> Vec<usize> {
+    [Backend::TreeWalker, Backend::Vm].into_iter().map(|backend| {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+
+        let dropped: Rc<Cell<usize>> = Rc::new(Cell::new(0));
+        let counted: Rc<Cell<usize>> = dropped.clone();
+        lox.define_native("tracker", 0, move |_, _| Ok(Value::Host(LoxHost::new(Tracker { dropped: counted.clone() }))));
+        lox.define_native("iterate", 1, |ctx, mut args| LoxIterator::over(args.remove(0), ctx.heap()));
+
+        lox.eval(source).unwrap();
+        lox.collect_garbage();
+        dropped.get()
+    }).collect()
+}
+
+#[test]
+fn classes_which_refer_to_themselves_through_statics_are_freed() {
+    let source: &str = "
+        fun makeClass() {
+            class A {}
+            A.me = A;
+            A.tracker = tracker();
+        }
+
+        for (var i = 0; i < 10; i = i + 1) makeClass();
+    ";
+
+    assert_eq!(dropped_trackers(source), vec![10, 10]);
+}
+
+#[test]
+fn lists_which_hold_an_iterator_over_themselves_are_freed() {
+    let source: &str = "
+        fun makeList() {
+            var list = [tracker()];
+            list.push(iterate(list));
+        }
+
+        for (var i = 0; i < 10; i = i + 1) makeList();
+    ";
+
+    assert_eq!(dropped_trackers(source), vec![10, 10]);
+}
+
+#[test]
+fn generators_stored_in_a_scope_they_capture_are_freed() {
+    let source: &str = "
+        fun makeGenerator() {
+            var kept = tracker();
+            var generator = nil;
+
+            fun body() {
+                yield kept;
+                yield generator;
+            }
+
+            generator = body();
+            generator.next();
+        }
+
+        for (var i = 0; i < 10; i = i + 1) makeGenerator();
+    ";
+
+    assert_eq!(dropped_trackers(source), vec![10, 10]);
+}
+
+#[test]
+fn lists_which_hold_a_generator_looping_over_them_are_freed() {
+    let source: &str = "
+        fun walk(list) {
+            for (item in list) yield item;
+        }
+
+        fun makeWalk() {
+            var list = [tracker()];
+            var walking = walk(list);
+            walking.next();
+            list.push(walking);
+        }
+
+        for (var i = 0; i < 10; i = i + 1) makeWalk();
+    ";
+
+    assert_eq!(dropped_trackers(source), vec![10, 10]);
+}
@@ -0,0 +1,38 @@
+use lox_rs::{Backend, Lox, LoxError};
+use lox_rs::types::values::Value;
+
+#[test]
+fn yield_evaluates_to_the_sent_value() {
+    let source: &str = "fun add() { var total = 0; while (true) { var amount = yield total; total = total + amount; } } var adder = add(); adder.next();";
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+        lox.eval(source).unwrap();
+
+        assert_eq!(lox.eval("adder.send(2);").unwrap(), Value::Int(2), "{backend:?}");
+        assert_eq!(lox.eval("adder.send(3);").unwrap(), Value::Int(5), "{backend:?}");
+        assert!(matches!(lox.eval("adder.next();"), Err(LoxError::ValueError(..))), "{backend:?}");
+    }
+}
+
+#[test]
+fn yield_inside_other_expressions_gives_the_sent_value() {
+    let source: &str = "fun f(a, b) { return a + b; } fun g() { var items = [yield 1]; return items[0] + f(yield, (yield) + \"!\"); }";
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+        lox.eval(source).unwrap();
+        lox.eval("var gen = g(); gen.next(); gen.send(\"a\"); gen.send(\"b\");").unwrap();
+
+        assert_eq!(lox.eval("gen.send(\"c\");").unwrap(), Value::Str(String::from("abc!")), "{backend:?}");
+    }
+}
+
+#[test]
+fn yield_in_a_static_field_initializer_is_an_error() {
+    let mut lox = Lox::new();
+    let result: Result<Value, LoxError> = lox.eval("fun f() { class A { static x = yield 1; } }");
+    assert!(matches!(result, Err(LoxError::ParseError(_, ref message)) if message == "Can't yield from a static field initializer."));
+}
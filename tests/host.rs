@@ -0,0 +1,107 @@
+// LoxError carries whole AST nodes and values, as it does in the library.
+#![allow(clippy::result_large_err)]
+
+use std::{cell::RefCell, rc::Rc};
+use lox_rs::{Lox, LoxError};
+use lox_rs::interpreter::context::NativeContext;
+use lox_rs::types::values::{Value, host::{HostObject, LoxHost}};
+
+/// A counter which scripts can read, set, step and watch.
+struct Counter {
+    count: i64
+}
+
+impl HostObject for Counter {
+    fn type_name(&self) -> String {
+        String::from("Counter")
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "count" => Some(Value::Int(self.count)),
+            _ => None
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Value) -> Result<(), LoxError> {
+        match (name, value) {
+            ("count", Value::Int(count)) => {
+                self.count = count;
+                Ok(())
+            },
+            (_, value) => Err(LoxError::ValueError(value, format!("Cannot set {name}.")))
+        }
+    }
+
+    fn method_arity(&self, name: &str) -> Option<usize> {
+        match name {
+            "step" => Some(1),
+            "watch" => Some(1),
+            _ => None
+        }
+    }
+
+    fn call_method(&mut self, name: &str, ctx: &mut NativeContext, mut args: Vec<Value>) -> Result<Value, LoxError> {
+        let arg: Value = args.pop().unwrap_or(Value::Nil());
+
+        match (name, arg) {
+            ("step", Value::Int(by)) => {
+                self.count += by;
+                Ok(Value::Int(self.count))
+            },
+            ("watch", callback) => ctx.call(callback, vec![Value::Int(self.count)]),
+            (_, arg) => Err(LoxError::ValueError(arg, String::from("Invalid argument.")))
+        }
+    }
+}
+
+fn counter(lox: &mut Lox) -> Rc<RefCell<dyn HostObject>> {
+    let host: LoxHost = LoxHost::new(Counter { count: 0 });
+    let shared: Rc<RefCell<dyn HostObject>> = host.get_ref();
+    lox.set_global("counter", Value::Host(host));
+    shared
+}
+
+#[test]
+fn host_properties_are_read_and_written() {
+    let mut lox = Lox::new();
+    let shared: Rc<RefCell<dyn HostObject>> = counter(&mut lox);
+
+    lox.eval("counter.count = 5;").unwrap();
+    assert_eq!(lox.eval("counter.count;").unwrap(), Value::Int(5));
+    assert_eq!(shared.borrow().get("count"), Some(Value::Int(5)));
+    assert!(matches!(lox.eval("counter.count = \"five\";"), Err(LoxError::ValueError(..))));
+    assert!(matches!(lox.eval("counter.missing;"), Err(LoxError::NameError(..))));
+}
+
+#[test]
+fn host_methods_are_dispatched_to_rust() {
+    let mut lox = Lox::new();
+    counter(&mut lox);
+
+    assert_eq!(lox.eval("counter.step(2); counter.step(3);").unwrap(), Value::Int(5));
+    assert_eq!(lox.eval("var step = counter.step; step(1);").unwrap(), Value::Int(6));
+    assert_eq!(lox.eval("counter.watch(fun (n) { return n * 2; });").unwrap(), Value::Int(12));
+    assert!(matches!(lox.eval("counter.step();"), Err(LoxError::ArgumentError(..))));
+}
+
+#[test]
+fn host_prints_with_its_type_name() {
+    let mut lox = Lox::new();
+    counter(&mut lox);
+
+    assert_eq!(lox.eval("\"${counter}\";").unwrap(), Value::Str(String::from("Instance of Counter")));
+}
+
+#[test]
+fn host_used_by_its_own_method_is_an_error() {
+    let mut lox = Lox::new();
+    counter(&mut lox);
+
+    let result: Result<Value, LoxError> = lox.eval("counter.watch(fun (n) { return counter.count; });");
+    assert!(matches!(result, Err(LoxError::ValueError(_, ref message)) if message == "Object is in use by one of its own methods."));
+    assert!(matches!(lox.eval("counter.watch(fun (n) { counter.step(1); });"), Err(LoxError::ValueError(..))));
+
+    // The object can be used again once the method has returned
+    assert_eq!(lox.eval("counter.count;").unwrap(), Value::Int(0));
+}
@@ -0,0 +1,32 @@
+use lox_rs::{Backend, Lox, LoxError};
+use lox_rs::types::values::Value;
+
+#[test]
+fn increments_work_as_statements() {
+    let mut lox = Lox::new();
+
+    assert_eq!(lox.eval("var i = 0; i++; ++i; i--; i;").unwrap(), Value::Int(1));
+    assert_eq!(lox.eval("var n = 0; for (var j = 0; j < 3; j++) n += j; n;").unwrap(), Value::Int(3));
+}
+
+#[test]
+fn increments_inside_expressions_are_syntax_errors() {
+    for source in ["var i = 0; print i++;", "var i = 0; var j = i++ + 1;", "var i = 0; var l = [--i];", "var i = 0; var f = () => i++;",
+                   "var i = 0; clock(i++);", "var i = 0; var j = 1; i + j++;", "var i = 0; i = ++i;"] {
+        let mut lox = Lox::new();
+        let result: Result<Value, LoxError> = lox.eval(source);
+        assert!(matches!(result, Err(LoxError::ParseError(_, ref message)) if message == "Increments can only be used as statements."), "{source}");
+    }
+}
+
+#[test]
+fn compound_division_by_zero_is_a_runtime_error() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        for source in ["var i = 1; i /= 0;", "var i = 1; i %= 0;", "var l = [1]; l[0] /= 0;"] {
+            let mut lox = Lox::new();
+            lox.set_backend(backend);
+            let result: Result<Value, LoxError> = lox.eval(source);
+            assert!(matches!(result, Err(LoxError::ValueError(Value::Int(0), ref message)) if message == "Division by zero."), "{source}");
+        }
+    }
+}
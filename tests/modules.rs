@@ -0,0 +1,24 @@
+// LoxError carries whole AST nodes and values, as it does in the library.
+#![allow(clippy::result_large_err)]
+
+use std::{env, fs, path::PathBuf, process};
+use lox_rs::{Backend, Lox};
+use lox_rs::types::values::Value;
+
+#[test]
+fn host_natives_are_visible_in_modules() {
+    let dir: PathBuf = env::temp_dir().join(format!("lox-rs-natives-{}", process::id()));
+    fs::create_dir_all(&dir).expect("Could not create the module directory.");
+    fs::write(dir.join("secret.lox"), "var v = secret();").expect("Could not write the module.");
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+        lox.set_script_path(dir.join("main.lox"));
+        lox.define_native("secret", 0, |_, _| Ok(Value::Int(42)));
+
+        assert_eq!(lox.eval("from \"secret.lox\" import v; v;").unwrap(), Value::Int(42), "{backend:?}");
+    }
+
+    fs::remove_dir_all(&dir).expect("Could not remove the module directory.");
+}
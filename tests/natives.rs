@@ -0,0 +1,64 @@
+// LoxError carries whole AST nodes and values, as it does in the library.
+#![allow(clippy::result_large_err)]
+
+use std::{cell::Cell, rc::Rc};
+use lox_rs::{Backend, Lox, LoxError};
+use lox_rs::types::values::Value;
+
+#[test]
+fn native_keeps_captured_state() {
+    let mut lox = Lox::new();
+    let calls: Rc<Cell<i64>> = Rc::new(Cell::new(0));
+    let counted: Rc<Cell<i64>> = calls.clone();
+
+    lox.define_native("tick", 0, move |_, _| {
+        counted.set(counted.get() + 1);
+        Ok(Value::Int(counted.get()))
+    });
+
+    assert_eq!(lox.eval("tick(); tick(); tick();").unwrap(), Value::Int(3));
+    assert_eq!(calls.get(), 3);
+}
+
+#[test]
+fn native_receives_arguments() {
+    let mut lox = Lox::new();
+    lox.define_native("join", 2, |_, args| Ok(Value::Str(format!("{}-{}", args[0], args[1]))));
+
+    assert_eq!(lox.eval("join(\"a\", 1);").unwrap(), Value::Str(String::from("a-1")));
+    assert!(matches!(lox.eval("join(\"a\");"), Err(LoxError::ArgumentError(..))));
+}
+
+#[test]
+fn native_errors_reach_the_caller() {
+    let mut lox = Lox::new();
+    lox.define_native("fail", 0, |_, _| Err(LoxError::NameError(String::from("fail"), String::from("Failed."))));
+
+    assert!(matches!(lox.eval("fail();"), Err(LoxError::NameError(..))));
+}
+
+#[test]
+fn native_calls_back_into_lox_closure() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox = Lox::new();
+        lox.set_backend(backend);
+        lox.define_native("twice", 2, |ctx, mut args| {
+            let value: Value = args.pop().unwrap_or(Value::Nil());
+            let callback: Value = args.pop().unwrap_or(Value::Nil());
+            let once: Value = ctx.call(callback.clone(), vec![value])?;
+            ctx.call(callback, vec![once])
+        });
+
+        let source: &str = "var step = 3; fun add(n) { return n + step; } twice(add, 1);";
+        assert_eq!(lox.eval(source).unwrap(), Value::Int(7), "{backend:?}");
+    }
+}
+
+#[test]
+fn native_callback_errors_reach_the_caller() {
+    let mut lox = Lox::new();
+    lox.define_native("apply", 1, |ctx, mut args| ctx.call(args.pop().unwrap_or(Value::Nil()), Vec::new()));
+
+    assert!(matches!(lox.eval("apply(fun () { throw \"oops\"; });"), Err(LoxError::Thrown(Value::Str(_)))));
+    assert!(matches!(lox.eval("apply(1);"), Err(LoxError::ValueError(..))));
+}
@@ -0,0 +1,53 @@
+use lox_rs::{Backend, Lox};
+use lox_rs::interpreter::output::OutputBuffer;
+
+/// Runs a program, returning its exit code, output and diagnostics.
+fn run(backend: Backend, source: &str) -> (i32, String, String) {
+    let (output, diagnostics): (OutputBuffer, OutputBuffer) = (OutputBuffer::new(), OutputBuffer::new());
+    let mut lox = Lox::new();
+    lox.set_backend(backend);
+    lox.set_output(output.clone());
+    lox.set_diagnostics(diagnostics.clone());
+
+    let code: i32 = lox.run(source);
+    (code, output.contents(), diagnostics.contents())
+}
+
+#[test]
+fn print_writes_to_output() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        assert_eq!(run(backend, "print 1; print \"two\";"), (0, String::from("1\ntwo\n"), String::new()));
+    }
+}
+
+#[test]
+fn runtime_errors_write_to_diagnostics() {
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let (code, output, diagnostics): (i32, String, String) = run(backend, "print \"before\"; print missing;");
+
+        assert_eq!(code, 5);
+        assert_eq!(output, "before\n");
+        assert_eq!(diagnostics, "[Name Error] Error for 'missing': Undefined variable.\n");
+    }
+}
+
+#[test]
+fn every_syntax_error_writes_to_diagnostics() {
+    let (code, output, diagnostics): (i32, String, String) = run(Backend::TreeWalker, "print 1\nvar = 2;");
+
+    assert_eq!(code, 1);
+    assert_eq!(output, "");
+    assert_eq!(diagnostics.lines().count(), 2);
+}
+
+#[test]
+fn output_buffer_can_be_cleared() {
+    let output: OutputBuffer = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+
+    lox.run("print 1;");
+    output.clear();
+    lox.run("print 2;");
+    assert_eq!(output.contents(), "2\n");
+}